@@ -2,12 +2,17 @@ require 'sinatra'
 require 'json'
 
 ISSUE_STATUSES = [
-  { id: 1, name: "Solved"      },
-  { id: 2, name: "Rejected"    },
+  { id: 1, name: "Solved",      is_closed: true },
+  { id: 2, name: "Rejected",    is_closed: true },
   { id: 3, name: "In Progress" },
   { id: 4, name: "Interrupted" },
 ]
 
+USERS = [
+  { id: 1, login: "jdoe",   firstname: "John", lastname: "Doe"   },
+  { id: 2, login: "jsmith", firstname: "Jane", lastname: "Smith" },
+]
+
 after do
   logger.info params.inspect
 end
@@ -19,3 +24,246 @@ end
 put '/issues/:id.json' do
   [200, {}, []]
 end
+
+ISSUES = [
+  {
+    id: 1, subject: "Crash on startup", description: "It crashes",
+    project: { id: 1, name: "rdm" }, tracker: { id: 1, name: "Bug" },
+    status: { id: 3, name: "In Progress" }, priority: { id: 2, name: "Normal" },
+    author: { id: 1, name: "John Doe" }, assigned_to: { id: 1, name: "John Doe" },
+    start_date: "2015-04-01", due_date: nil, done_ratio: 0,
+    created_on: "2015-04-01T12:00:00Z", updated_on: "2015-04-02T12:00:00Z",
+    custom_fields: [
+      { id: 1, name: "Customer", value: "ACME" },
+      { id: 2, name: "Platforms", multiple: true, value: ["Linux", "Mac"] },
+    ],
+  },
+]
+
+def paginate(key, items)
+  offset = (params[:offset] || 0).to_i
+  limit  = (params[:limit] || 25).to_i
+
+  { key => items.drop(offset).take(limit), total_count: items.length, offset: offset, limit: limit }.to_json
+end
+
+get '/issues.json' do
+  paginate(:issues, ISSUES)
+end
+
+get '/users.json' do
+  paginate(:users, USERS)
+end
+
+get '/users/current.json' do
+  { user: USERS.first }.to_json
+end
+
+get '/trackers.json' do
+  { trackers: [{ id: 1, name: "Bug" }, { id: 2, name: "Feature" }] }.to_json
+end
+
+post '/issues/:id/watchers.json' do
+  [204, {}, []]
+end
+
+delete '/issues/:id/watchers/:user_id.json' do
+  [204, {}, []]
+end
+
+get '/issues/:id.json' do
+  issue = ISSUES.find { |i| i[:id] == params[:id].to_i } or halt 404
+
+  journals = [{
+    id: 1, user: { id: 1, name: "John Doe" }, notes: "Looking into it",
+    created_on: "2015-04-02T12:00:00Z",
+    details: [{ property: "attr", name: "status_id", old_value: "1", new_value: "3" }],
+  }]
+
+  { issue: issue.merge(journals: journals, attachments: [], relations: [], children: [], watchers: []) }.to_json
+end
+
+get '/enumerations/issue_priorities.json' do
+  { issue_priorities: [{ id: 1, name: "Low" }, { id: 2, name: "Normal" }, { id: 3, name: "High" }] }.to_json
+end
+
+post '/projects/:project/issues.json' do
+  [201, { issue: { id: ISSUES.length + 1 } }.to_json]
+end
+
+VERSIONS = [
+  { id: 1, project: { id: 1, name: "rdm" }, name: "0.1", status: "closed", due_date: "2015-04-01",
+    sharing: "none", created_on: "2015-03-01T12:00:00Z", updated_on: "2015-04-01T12:00:00Z" },
+  { id: 2, project: { id: 1, name: "rdm" }, name: "0.2", status: "open", due_date: "2015-05-01",
+    sharing: "none", created_on: "2015-03-01T12:00:00Z", updated_on: "2015-04-01T12:00:00Z" },
+]
+
+get '/projects/:project/versions.json' do
+  { versions: VERSIONS, total_count: VERSIONS.length }.to_json
+end
+
+post '/projects/:project/versions.json' do
+  version = JSON.parse(request.body.read)["version"]
+  [201, { version: VERSIONS.last.merge(id: VERSIONS.length + 1, name: version["name"]) }.to_json]
+end
+
+get '/versions/:id.json' do
+  version = VERSIONS.find { |v| v[:id] == params[:id].to_i } or halt 404
+  { version: version }.to_json
+end
+
+put '/versions/:id.json' do
+  [200, {}, []]
+end
+
+get '/projects/:project/issue_categories.json' do
+  { issue_categories: [{ id: 1, name: "CLI" }], total_count: 1 }.to_json
+end
+
+get '/custom_fields.json' do
+  { custom_fields: [
+    { id: 1, name: "Customer", customized_type: "issue", field_format: "string" },
+    { id: 2, name: "Platforms", customized_type: "issue", field_format: "list", multiple: true,
+      possible_values: [{ value: "Linux" }, { value: "Mac" }, { value: "Windows" }] },
+  ] }.to_json
+end
+
+RELATIONS = [
+  { id: 1, issue_id: 1, issue_to_id: 2, relation_type: "blocks", delay: nil },
+]
+
+get '/issues/:id/relations.json' do
+  { relations: RELATIONS.select { |r| [r[:issue_id], r[:issue_to_id]].include?(params[:id].to_i) } }.to_json
+end
+
+post '/issues/:id/relations.json' do
+  relation = JSON.parse(request.body.read)["relation"]
+  [201, { relation: relation.merge("id" => RELATIONS.length + 1, "issue_id" => params[:id].to_i) }.to_json]
+end
+
+delete '/relations/:id.json' do
+  [200, {}, []]
+end
+
+post '/uploads.json' do
+  [201, { upload: { token: "7167.ed1ccdb093229ca1bd0b043618d88743" } }.to_json]
+end
+
+get '/attachments/:id.json' do
+  { attachment: {
+    id: params[:id].to_i, filename: "notes.txt", filesize: 5, content_type: "text/plain",
+    content_url: "/attachments/download/#{params[:id]}/notes.txt",
+    author: { id: 1, name: "John Doe" }, created_on: "2015-04-02T12:00:00Z",
+  } }.to_json
+end
+
+get '/attachments/download/:id/:filename' do
+  "hello"
+end
+
+TIME_ENTRIES = [
+  {
+    id: 1, project: { id: 1, name: "rdm" }, issue: { id: 1 }, user: { id: 1, name: "John Doe" },
+    activity: { id: 9, name: "Development" }, hours: 1.5, comments: "Investigation",
+    spent_on: "2015-04-02", created_on: "2015-04-02T12:00:00Z", updated_on: "2015-04-02T12:00:00Z",
+  },
+]
+
+get '/enumerations/time_entry_activities.json' do
+  { time_entry_activities: [{ id: 8, name: "Design" }, { id: 9, name: "Development" }] }.to_json
+end
+
+get '/time_entries.json' do
+  paginate(:time_entries, TIME_ENTRIES)
+end
+
+post '/time_entries.json' do
+  entry = JSON.parse(request.body.read)["time_entry"]
+  [201, { time_entry: TIME_ENTRIES.first.merge(id: TIME_ENTRIES.length + 1, hours: entry["hours"].to_f) }.to_json]
+end
+
+put '/time_entries/:id.json' do
+  [200, {}, []]
+end
+
+delete '/time_entries/:id.json' do
+  [200, {}, []]
+end
+
+PROJECTS = [
+  { id: 1, name: "rdm", identifier: "rdm", description: "A command-line redmine client",
+    is_public: true, created_on: "2015-04-01T12:00:00Z", updated_on: "2015-04-01T12:00:00Z" },
+  { id: 2, name: "rdm docs", identifier: "rdm-docs", parent: { id: 1, name: "rdm" },
+    is_public: true, created_on: "2015-04-01T12:00:00Z", updated_on: "2015-04-01T12:00:00Z" },
+]
+
+get '/projects.json' do
+  paginate(:projects, PROJECTS)
+end
+
+get '/projects/:id.json' do
+  project = PROJECTS.find { |p| p[:identifier] == params[:id] || p[:id].to_s == params[:id] } or halt 404
+  { project: project.merge(trackers: [{ id: 1, name: "Bug" }], issue_categories: [],
+                           enabled_modules: [{ id: 1, name: "issue_tracking" }]) }.to_json
+end
+
+get '/projects/:id/memberships.json' do
+  paginate(:memberships, [{ id: 1, user: { id: 1, name: "John Doe" }, roles: [{ id: 3, name: "Manager" }] }])
+end
+
+post '/projects.json' do
+  project = JSON.parse(request.body.read)["project"]
+  [201, { project: project.merge("id" => PROJECTS.length + 1, "created_on" => "2015-04-03T12:00:00Z",
+                                 "updated_on" => "2015-04-03T12:00:00Z") }.to_json]
+end
+
+WIKI_PAGES = [
+  { title: "Wiki", version: 3, created_on: "2015-03-01T12:00:00Z", updated_on: "2015-04-01T12:00:00Z" },
+  { title: "Installation", parent: { title: "Wiki" }, version: 1,
+    created_on: "2015-03-02T12:00:00Z", updated_on: "2015-03-02T12:00:00Z" },
+]
+
+get '/projects/:project/wiki/index.json' do
+  { wiki_pages: WIKI_PAGES }.to_json
+end
+
+get %r{/projects/([^/]+)/wiki/([^/]+?)(?:/(\d+))?\.json} do |project, title, version|
+  page = WIKI_PAGES.find { |p| p[:title] == title } or halt 404
+  version = (version || page[:version]).to_i
+  halt 404 if version > page[:version]
+
+  { wiki_page: page.merge(text: "h1. #{title}\n\nVersion #{version}", version: version,
+                          author: { id: 1, name: "John Doe" }, comments: "") }.to_json
+end
+
+put '/projects/:project/wiki/:title.json' do
+  page = WIKI_PAGES.find { |p| p[:title] == params[:title] }
+  sent = JSON.parse(request.body.read)["wiki_page"]
+
+  halt 409 if page && sent["version"] && sent["version"] != page[:version]
+  [page ? 204 : 201, {}, []]
+end
+
+SEARCH_RESULTS = [
+  { id: 1, title: "Bug #1 (In Progress): Crash on start", type: "issue", url: "http://localhost:4567/issues/1",
+    description: "The program crashes", datetime: "2015-04-01T12:00:00Z" },
+  { id: 1, title: "Wiki: Installation", type: "wiki-page", url: "http://localhost:4567/projects/rdm/wiki/Installation",
+    description: "How to install", datetime: "2015-03-02T12:00:00Z" },
+]
+
+get '/search.json' do
+  paginate(:results, SEARCH_RESULTS)
+end
+
+get '/projects/:project/search.json' do
+  paginate(:results, SEARCH_RESULTS)
+end
+
+get '/queries.json' do
+  queries = [
+    { id: 1, name: "Open bugs", is_public: true, project_id: 1 },
+    { id: 2, name: "My watched issues", is_public: false },
+  ]
+
+  paginate(:queries, queries)
+end
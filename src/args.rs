@@ -20,36 +20,392 @@ use std::{fmt, error};
 const USAGE: &'static str = "
 Usage:
     rdm --help
-    rdm issues [--assigned-to=<user>] [--open|--closed|--status=<status>]
-    rdm issue <issue-number> update --status=<status>
+    rdm issues [--assigned-to=<user>] [--open|--closed|--status=<status>] [--limit=<n>]
+               [--project=<project>] [--subprojects|--no-subprojects] [--tracker=<tracker>]
+               [--priority=<priority>] [--author=<user>] [--category=<category>]
+               [--target-version=<version>] [--parent=<issue>] [--subject=<subject>]
+               [--created-on=<date>] [--updated-on=<date>] [--closed-on=<date>]
+               [--cf=<field-value>...] [--sort=<order>] [--columns=<columns>] [--group-by=<column>]
+    rdm issues --query=<query> [--limit=<n>] [--sort=<order>] [--columns=<columns>]
+               [--group-by=<column>]
+    rdm queries [--limit=<n>]
+    rdm watching [--limit=<n>]
+    rdm search <terms>... [--project=<project>] [--issues] [--wiki] [--news] [--changesets]
+                          [--titles-only] [--open-issues] [--limit=<n>]
+    rdm projects [--limit=<n>]
+    rdm project create --name=<name> --identifier=<identifier> [--description=<text>]
+                       [--parent-project=<project>] [--private|--public]
+    rdm project <project>
+    rdm versions <project> [--limit=<n>]
+    rdm version create <project> --name=<name> [--due-date=<date>] [--description=<text>]
+                                 [--status=<status>]
+    rdm version <version-id> [show]
+    rdm version <version-id> update [--name=<name>] [--due-date=<date>] [--description=<text>]
+                                    [--status=<status>]
+    rdm version <version-id> close
+    rdm version <version-id> rollover --to=<version> [--close] [--yes]
+    rdm wiki sync <project> <dir> [--push|--pull]
+    rdm wiki <project> [<page>]
+    rdm wiki <project> <page> show [--version=<n>]
+    rdm wiki <project> <page> edit [--comment=<text>]
+    rdm wiki <project> <page> history [--limit=<n>]
+    rdm changelog <version> [--project=<project>] [--format=<format>]
+    rdm issue create --subject=<subject> [--project=<project>] [--tracker=<tracker>]
+                     [--description=<text>] [--priority=<priority>] [--assigned-to=<user>]
+                     [--parent=<issue>] [--start-date=<date>] [--due-date=<date>]
+                     [--estimated-hours=<hours>] [--cf=<field-value>...]
+    rdm issue <issue-number> show
+    rdm issue <issue-number> relations
+    rdm issue <issue-number> relate <relation-type> <other-issue> [--delay=<days>]
+    rdm relation <relation-id> delete
+    rdm issue <issue-number> watch [--user=<user>]
+    rdm issue <issue-number> unwatch [--user=<user>]
+    rdm issue <issue-number> attach <file>...
+    rdm attachment <attachment-id> download [--output=<path>]
+    rdm log <issue-number> <hours> [--activity=<activity>] [--date=<date>] [--comment=<text>]
+    rdm time list [--user=<user>] [--issue=<issue>] [--from=<date>] [--to=<date>] [--limit=<n>]
+    rdm time <entry-id> edit [--hours=<hours>] [--issue=<issue>] [--activity=<activity>]
+                             [--date=<date>] [--comment=<text>]
+    rdm time <entry-id> delete
+    rdm time import <file> --format=<format> [--yes]
+    rdm timesheet [--week=<week>] [--user=<user>] [--edit]
+    rdm timer start <issue-number> [--activity=<activity>] [--comment=<text>]
+    rdm timer stop [--activity=<activity>] [--comment=<text>]
+    rdm timer status
+    rdm timer discard
+    rdm issue <issue-number> update [--status=<status>] [--subject=<subject>]
+                                    [--assigned-to=<user>] [--priority=<priority>]
+                                    [--tracker=<tracker>] [--done-ratio=<percent>]
+                                    [--start-date=<date>] [--due-date=<date>]
+                                    [--estimated-hours=<hours>] [--target-version=<version>]
+                                    [--category=<category>] [--parent=<issue>]
+                                    [--private|--public] [--cf=<field-value>...]
+                                    [--notes=<text>|--notes-file=<file>] [--private-notes]
     rdm issue <issue-number> close [--status=<status>]
+                                   [--notes=<text>|--notes-file=<file>] [--private-notes]
 
 Options
     -h, --help                Show this message
     -s, --status=<status>     A status name (case-insensitive). Optional when closing if the user
                               has the 'default_close_status' setting on the config file.
+                              For versions, one of: open, locked, closed.
     -a, --assigned-to=<user>  The user whose issues we are searching. It can be an exact match
-                              of the name or a partial, case-insensitive match of the user's name.
+                              of the name or a partial, case-insensitive match of the user's login,
+                              first, last or full name. Use 'me' for yourself.
+    -p, --project=<project>   A project identifier, id or name (case-insensitive, partial matches
+                              allowed). When creating issues, defaults to the 'default_project'
+                              setting on the config file.
+    --subprojects             List the issues of the project's subprojects too
+    --no-subprojects          List only the issues of the project itself
+    --author=<user>           The user who created the issues, matched the same way as --assigned-to
+    --created-on=<date>       The day issues were created, as YYYY-MM-DD
+    --updated-on=<date>       The day issues were last updated, as YYYY-MM-DD
+    --closed-on=<date>        The day issues were closed, as YYYY-MM-DD
+    --sort=<order>            How Redmine sorts the issues: comma-separated attributes, each
+                              optionally followed by ':desc' (e.g. priority:desc,updated_on)
+    --columns=<columns>       Show the issues as a table with these comma-separated columns. Any of:
+                              id, project, tracker, status, priority, author, assignee, subject,
+                              version, category, start_date, due_date, done_ratio,
+                              estimated_hours, created_on, updated_on
+    --group-by=<column>       Group the issues by one of the columns, with a count for each group.
+                              Without --columns, shows id, status, assignee and subject.
+    --issues                  Search issues. Without --issues, --wiki, --news or --changesets,
+                              everything is searched.
+    --wiki                    Search wiki pages
+    --news                    Search news
+    --changesets              Search changesets
+    --titles-only             Only match titles
+    --open-issues             Only match open issues
+    -q, --query=<query>       A query saved on Redmine, by name (case-insensitive, partial matches
+                              allowed) or id. Lists the same issues as the query on the browser.
+    --name=<name>             The name of a project or version
+    --identifier=<identifier> The identifier of a new project, as used on URLs
+    --parent-project=<project>
+                              The parent of a new project, matched the same way as --project
+    --subject=<subject>       The issue subject
+    --description=<text>      The issue description
+    -t, --tracker=<tracker>   A tracker name (case-insensitive, partial matches allowed) or id
+    --priority=<priority>     A priority name (case-insensitive, partial matches allowed) or id
+    --parent=<issue>          The number of the parent issue
+    --start-date=<date>       Start date, as YYYY-MM-DD
+    --due-date=<date>         Due date, as YYYY-MM-DD
+    --estimated-hours=<hours> Estimated time, in hours
+    --cf=<field-value>        A custom field value, as <name>=<value>. The name may also be the
+                              field's id or cf_<id> (only ids work for non-administrators).
+                              Repeat the same field to give several values to a multi-value field.
+    --done-ratio=<percent>    Percentage done, from 0 to 100
+    --target-version=<version>
+                              A version name (case-insensitive, partial matches allowed) or id,
+                              from the issue's project
+    --category=<category>     A category name (case-insensitive, partial matches allowed) or id,
+                              from the issue's project
+    --private                 Make the issue private
+    --public                  Make the issue public
+    -n, --notes=<text>        Notes to add to the issue. Without --notes or --notes-file, $EDITOR
+                              is opened to write them when running on a terminal; leaving it
+                              empty adds no notes.
+    --notes-file=<file>       Read the notes from a file. Use '-' to read from stdin.
+    --private-notes           Make the notes visible only to users allowed to see private notes
+    -l, --limit=<n>           Maximum number of items to list. Lists are fetched in full when omitted.
+    --delay=<days>            Days between the end of an issue and the start of the next one, for
+                              'precedes' and 'follows' relations
+    -o, --output=<path>       Where to save the downloaded file. Defaults to the attachment's
+                              file name, on the current directory. Use '-' for stdout.
+    --activity=<activity>     A time entry activity name (case-insensitive, partial matches
+                              allowed) or id
+    --date=<date>             The day the time was spent, as YYYY-MM-DD. Defaults to today.
+    --comment=<text>          A comment for the time entry, or for the wiki page change
+    --push                    Only upload pages changed locally, and keep the local side of
+                              pages changed on both sides
+    --pull                    Only download pages changed on the server, and keep the
+                              server side of pages changed on both sides
+    --version=<n>             A version of a wiki page, as shown by
+                              'rdm wiki <project> <page> history'
+    --hours=<hours>           Time spent, e.g. 1.5, 1:30 or 1h30
+    -i, --issue=<issue>       The number of an issue
+    -u, --user=<user>         A user, matched the same way as --assigned-to. When watching or
+                              unwatching issues, defaults to yourself.
+    -f, --format=<format>     The format of an imported file: 'csv' (with date, issue, hours and
+                              optional activity and comment columns) or 'timew' (the output of
+                              'timew export', with '#<issue>' and 'activity:<name>' tags).
+                              For changelogs, one of: markdown (the default), text, json.
+    -y, --yes                 Don't ask for confirmation
+    -w, --week=<week>         An ISO week, as YYYY-Www (e.g. 2015-W14). Defaults to the current week.
+    -e, --edit                Edit the timesheet on $EDITOR, applying the changed cells as new,
+                              updated or deleted time entries
+    --from=<date>             Only list entries from this day on, as YYYY-MM-DD
+    --to=<date>               Only list entries up to this day, as YYYY-MM-DD. When rolling a
+                              version over, the version to move its open issues to, matched the
+                              same way as --target-version.
+    --close                   Close the version after moving its open issues
     issue-number              The number of an issue
+    hours                     Time spent, e.g. 1.5, 1:30 or 1h30
+    entry-id                  The id of a time entry, as shown by 'rdm time list'
+    relation-type             One of: relates, duplicates, duplicated, blocks, blocked, precedes,
+                              follows, copied_to, copied_from
+    version                   A version id or, with --project or the 'default_project' setting,
+                              a version name (case-insensitive, partial matches allowed)
+    version-id                The id of a version, as shown by 'rdm versions <project>'
+    relation-id               The id of a relation, as shown by 'rdm issue <issue-number> relations'
+
+Issue filters
+    Besides plain values, the filters of 'rdm issues' accept Redmine's operators:
+        '*'              any value is set
+        '!*'             no value is set (e.g. --assigned-to='!*' for unassigned issues)
+        '>=<value>'      greater than or equal to (e.g. --created-on='>=2015-04-01')
+        '<=<value>'      less than or equal to
+        '><<from>|<to>'  between, inclusive (e.g. --updated-on='><2015-04-01|2015-04-30')
+        '~<text>'        contains (e.g. --subject='~crash')
 ";
 
+pub const ISSUE_COLUMNS: &'static [&'static str] = &[
+    "id", "project", "tracker", "status", "priority", "author", "assignee", "subject", "version",
+    "category", "start_date", "due_date", "done_ratio", "estimated_hours", "created_on", "updated_on",
+];
+
+/// The columns shown when grouping without --columns
+const DEFAULT_ISSUE_COLUMNS: &'static [&'static str] = &["id", "status", "assignee", "subject"];
+
+const RELATION_TYPES: &'static [&'static str] = &[
+    "relates", "duplicates", "duplicated", "blocks", "blocked", "precedes", "follows",
+    "copied_to", "copied_from",
+];
+
 #[derive(RustcDecodable)]
 #[allow(dead_code)]
 struct RawArgs {
     cmd_issue: bool,
     cmd_issues: bool,
+    cmd_projects: bool,
+    cmd_project: bool,
+    cmd_versions: bool,
+    cmd_version: bool,
+    cmd_rollover: bool,
+    cmd_changelog: bool,
+    cmd_wiki: bool,
+    cmd_history: bool,
+    cmd_sync: bool,
+    cmd_search: bool,
+    cmd_queries: bool,
+    cmd_create: bool,
+    cmd_show: bool,
+    cmd_relations: bool,
+    cmd_relate: bool,
+    cmd_relation: bool,
+    cmd_watch: bool,
+    cmd_unwatch: bool,
+    cmd_watching: bool,
+    cmd_delete: bool,
+    cmd_attach: bool,
+    cmd_attachment: bool,
+    cmd_download: bool,
+    cmd_log: bool,
+    cmd_time: bool,
+    cmd_list: bool,
+    cmd_edit: bool,
+    cmd_import: bool,
+    cmd_timesheet: bool,
+    cmd_timer: bool,
+    cmd_start: bool,
+    cmd_stop: bool,
+    cmd_status: bool,
+    cmd_discard: bool,
     cmd_update: bool,
     cmd_close: bool,
 
     arg_issue_number: Option<u32>,
+    arg_project: Option<String>,
+    arg_version_id: Option<u32>,
+    arg_version: Option<String>,
+    arg_page: Option<String>,
+    arg_dir: Option<String>,
+    arg_terms: Vec<String>,
+    arg_relation_type: Option<String>,
+    arg_other_issue: Option<u32>,
+    arg_relation_id: Option<u32>,
+    arg_file: Vec<String>,
+    arg_attachment_id: Option<u32>,
+    arg_hours: Option<String>,
+    arg_entry_id: Option<u32>,
 
     flag_assigned_to: Option<String>,
     flag_status: Option<String>,
+    flag_limit: Option<u32>,
+    flag_project: Option<String>,
+    flag_subject: Option<String>,
+    flag_description: Option<String>,
+    flag_tracker: Option<String>,
+    flag_priority: Option<String>,
+    flag_parent: Option<u32>,
+    flag_start_date: Option<String>,
+    flag_due_date: Option<String>,
+    flag_estimated_hours: Option<f64>,
+    flag_done_ratio: Option<u32>,
+    flag_target_version: Option<String>,
+    flag_category: Option<String>,
+    flag_private: bool,
+    flag_notes: Option<String>,
+    flag_notes_file: Option<String>,
+    flag_private_notes: bool,
+    flag_cf: Vec<String>,
+    flag_delay: Option<i32>,
+    flag_output: Option<String>,
+    flag_activity: Option<String>,
+    flag_date: Option<String>,
+    flag_comment: Option<String>,
+    flag_hours: Option<String>,
+    flag_issue: Option<u32>,
+    flag_user: Option<String>,
+    flag_from: Option<String>,
+    flag_to: Option<String>,
+    flag_week: Option<String>,
+    flag_edit: bool,
+    flag_format: Option<String>,
+    flag_yes: bool,
+    flag_close: bool,
+    flag_version: Option<u32>,
+    flag_push: bool,
+    flag_pull: bool,
+    flag_issues: bool,
+    flag_wiki: bool,
+    flag_news: bool,
+    flag_changesets: bool,
+    flag_titles_only: bool,
+    flag_open_issues: bool,
+    flag_query: Option<String>,
+    flag_subprojects: bool,
+    flag_no_subprojects: bool,
+    flag_author: Option<String>,
+    flag_created_on: Option<String>,
+    flag_updated_on: Option<String>,
+    flag_closed_on: Option<String>,
+    flag_sort: Option<String>,
+    flag_columns: Option<String>,
+    flag_group_by: Option<String>,
+    flag_name: Option<String>,
+    flag_identifier: Option<String>,
+    flag_parent_project: Option<String>,
+    flag_public: bool,
     flag_open: bool,
     flag_closed: bool,
     flag_help: bool,
 }
 
+/// A value to filter issues by, possibly with one of Redmine's filter operators
+#[derive(Debug, Clone)]
+pub enum Filter {
+    Is(String),
+    Any,
+    NoValue,
+    Between(String, String),
+    AtLeast(String),
+    AtMost(String),
+    Contains(String),
+}
+
+impl Filter {
+    fn parse(value: &str) -> Result<Filter, Error> {
+        if value == "*" {
+            return Ok(Filter::Any);
+        }
+
+        if value == "!*" {
+            return Ok(Filter::NoValue);
+        }
+
+        if value.starts_with("><") {
+            let mut bounds = value[2..].splitn(2, '|');
+
+            return match (bounds.next(), bounds.next()) {
+                (Some(from), Some(to)) => Ok(Filter::Between(from.to_string(), to.to_string())),
+                _ => Err(From::from("'><' filters must be given as ><<from>|<to>")),
+            };
+        }
+
+        if value.starts_with(">=") {
+            return Ok(Filter::AtLeast(value[2..].to_string()));
+        }
+
+        if value.starts_with("<=") {
+            return Ok(Filter::AtMost(value[2..].to_string()));
+        }
+
+        if value.starts_with("~") {
+            return Ok(Filter::Contains(value[1..].to_string()));
+        }
+
+        Ok(Filter::Is(value.to_string()))
+    }
+}
+
+/// The filters of `rdm issues`, other than the status
+#[derive(Debug)]
+pub struct IssueFilters {
+    pub project: Option<String>,
+    pub subprojects: Option<bool>,
+    pub assigned_to: Option<Filter>,
+    pub author: Option<Filter>,
+    pub tracker: Option<Filter>,
+    pub priority: Option<Filter>,
+    pub category: Option<Filter>,
+    pub target_version: Option<Filter>,
+    pub parent: Option<Filter>,
+    pub subject: Option<Filter>,
+    pub created_on: Option<Filter>,
+    pub updated_on: Option<Filter>,
+    pub closed_on: Option<Filter>,
+    pub custom_fields: Vec<(String, String)>,
+}
+
+/// How `rdm issues` orders and shows the issues. Without columns (given, or defaulted because
+/// of grouping), issues are listed one per line, as usual.
+#[derive(Debug)]
+pub struct IssueListOptions {
+    pub sort: Option<String>,
+    pub columns: Option<Vec<String>>,
+    pub group_by: Option<String>,
+}
+
 #[derive(Debug)]
 pub enum Status {
     AllOpen,
@@ -57,11 +413,139 @@ pub enum Status {
     Specific(String),
 }
 
+/// Issue attributes given on the command line. Names (status, tracker, priority, assignee, etc.)
+/// are still unresolved.
+#[derive(Debug)]
+pub struct IssueAttributes {
+    pub project: Option<String>,
+    pub status: Option<String>,
+    pub subject: Option<String>,
+    pub description: Option<String>,
+    pub tracker: Option<String>,
+    pub priority: Option<String>,
+    pub assigned_to: Option<String>,
+    pub parent: Option<u32>,
+    pub start_date: Option<String>,
+    pub due_date: Option<String>,
+    pub estimated_hours: Option<f64>,
+    pub done_ratio: Option<u32>,
+    pub target_version: Option<String>,
+    pub category: Option<String>,
+    pub is_private: Option<bool>,
+    pub custom_fields: Vec<(String, String)>,
+}
+
+impl IssueAttributes {
+    pub fn is_empty(&self) -> bool {
+        self.status.is_none() && self.subject.is_none() && self.description.is_none() &&
+            self.tracker.is_none() && self.priority.is_none() && self.assigned_to.is_none() &&
+            self.parent.is_none() && self.start_date.is_none() && self.due_date.is_none() &&
+            self.estimated_hours.is_none() && self.done_ratio.is_none() &&
+            self.target_version.is_none() && self.category.is_none() && self.is_private.is_none() &&
+            self.custom_fields.is_empty()
+    }
+}
+
+/// Time entry attributes given on the command line, with the activity name still unresolved
+#[derive(Debug)]
+pub struct TimeEntryAttributes {
+    pub issue: Option<u32>,
+    pub hours: Option<String>,
+    pub activity: Option<String>,
+    pub date: Option<String>,
+    pub comment: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum ImportFormat {
+    Csv,
+    Timewarrior,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum ChangelogFormat {
+    Markdown,
+    Text,
+    Json,
+}
+
+/// Which way a wiki sync may copy pages
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SyncDirection {
+    Both,
+    Push,
+    Pull,
+}
+
+/// What `rdm search` looks for; an empty list of types means every type
+#[derive(Debug)]
+pub struct SearchOptions {
+    pub types: Vec<&'static str>,
+    pub titles_only: bool,
+    pub open_issues: bool,
+}
+
+#[derive(Debug)]
+pub struct VersionAttributes {
+    pub name: Option<String>,
+    pub due_date: Option<String>,
+    pub description: Option<String>,
+    pub status: Option<String>,
+}
+
+/// Where the notes of an issue update come from
+#[derive(Debug)]
+pub enum NotesSource {
+    Text(String),
+    File(String),
+    /// Only when stdin is a terminal; otherwise there are no notes
+    Editor,
+}
+
 #[derive(Debug)]
 pub enum Args {
-    ListIssues  { assigned_to: Option<String>, status: Status },
-    UpdateIssue { number: u32, new_status: String },
-    CloseIssue  { number: u32, close_status: Option<String> },
+    ListIssues  { status: Status, filters: IssueFilters, limit: Option<u32>, options: IssueListOptions },
+    RunQuery { query: String, limit: Option<u32>, options: IssueListOptions },
+    ListQueries { limit: Option<u32> },
+    Search { terms: String, project: Option<String>, options: SearchOptions, limit: Option<u32> },
+    ListProjects { limit: Option<u32> },
+    ShowProject { project: String },
+    CreateProject { name: String, identifier: String, description: Option<String>,
+                    parent: Option<String>, is_public: Option<bool> },
+    ListVersions { project: String, limit: Option<u32> },
+    ShowVersion { id: u32 },
+    CreateVersion { project: String, attributes: VersionAttributes },
+    UpdateVersion { id: u32, attributes: VersionAttributes },
+    RolloverVersion { id: u32, to: String, close: bool, confirmed: bool },
+    ShowWikiIndex { project: String },
+    ShowWikiPage { project: String, page: String, version: Option<u32> },
+    EditWikiPage { project: String, page: String, comment: Option<String> },
+    WikiHistory { project: String, page: String, limit: Option<u32> },
+    SyncWiki { project: String, dir: String, direction: SyncDirection },
+    Changelog { version: String, project: Option<String>, format: ChangelogFormat },
+    CreateIssue(IssueAttributes),
+    ShowIssue   { number: u32 },
+    ListRelations  { number: u32 },
+    CreateRelation { number: u32, relation_type: String, other: u32, delay: Option<i32> },
+    DeleteRelation { id: u32 },
+    WatchIssue { number: u32, user: Option<String> },
+    UnwatchIssue { number: u32, user: Option<String> },
+    ListWatching { limit: Option<u32> },
+    AttachFiles { number: u32, paths: Vec<String> },
+    LogTime { attributes: TimeEntryAttributes },
+    ListTimeEntries { user: Option<String>, issue: Option<u32>, from: Option<String>,
+                      to: Option<String>, limit: Option<u32> },
+    EditTimeEntry { id: u32, attributes: TimeEntryAttributes },
+    DeleteTimeEntry { id: u32 },
+    ImportTime { path: String, format: ImportFormat, confirmed: bool },
+    Timesheet { week: Option<String>, user: Option<String>, edit: bool },
+    StartTimer { number: u32, activity: Option<String>, comment: Option<String> },
+    StopTimer { activity: Option<String>, comment: Option<String> },
+    TimerStatus,
+    DiscardTimer,
+    DownloadAttachment { id: u32, output: Option<String> },
+    UpdateIssue { number: u32, attributes: IssueAttributes, notes: NotesSource, private_notes: bool },
+    CloseIssue  { number: u32, close_status: Option<String>, notes: NotesSource, private_notes: bool },
 }
 
 #[derive(Debug)]
@@ -134,31 +618,516 @@ impl From<&'static str> for Error {
 }
 
 pub fn parse() -> Result<Args, Error> {
-    let raw: RawArgs = try!(Docopt::new(USAGE).and_then(|d| d.decode()));
+    let mut raw: RawArgs = try!(Docopt::new(USAGE).and_then(|d| d.decode()));
+    let custom_fields = try!(parse_custom_values(&raw.flag_cf));
 
     if raw.cmd_issues {
+        let options = try!(issue_list_options(&mut raw));
+
+        if let Some(query) = raw.flag_query {
+            return Ok(Args::RunQuery { query: query, limit: raw.flag_limit, options: options });
+        }
+
         let status = match raw.flag_status {
             Some(s) => Status::Specific(s),
             None if raw.flag_closed => Status::AllClosed,
             _ => Status::AllOpen,
         };
 
-        return Ok(Args::ListIssues {
+        if (raw.flag_subprojects || raw.flag_no_subprojects) && raw.flag_project.is_none() {
+            return Err(From::from("--subprojects and --no-subprojects need a --project"));
+        }
+
+        let filters = IssueFilters {
+            project: raw.flag_project,
+            subprojects: match (raw.flag_subprojects, raw.flag_no_subprojects) {
+                (true, _) => Some(true),
+                (_, true) => Some(false),
+                _         => None,
+            },
+            assigned_to: try!(filter(raw.flag_assigned_to)),
+            author: try!(filter(raw.flag_author)),
+            tracker: try!(filter(raw.flag_tracker)),
+            priority: try!(filter(raw.flag_priority)),
+            category: try!(filter(raw.flag_category)),
+            target_version: try!(filter(raw.flag_target_version)),
+            parent: raw.flag_parent.map(|number| Filter::Is(number.to_string())),
+            subject: try!(filter(raw.flag_subject)),
+            created_on: try!(date_filter(raw.flag_created_on)),
+            updated_on: try!(date_filter(raw.flag_updated_on)),
+            closed_on: try!(date_filter(raw.flag_closed_on)),
+            custom_fields: custom_fields,
+        };
+
+        return Ok(Args::ListIssues { status: status, filters: filters, limit: raw.flag_limit, options: options });
+    }
+
+    if raw.cmd_queries {
+        return Ok(Args::ListQueries { limit: raw.flag_limit });
+    }
+
+    if raw.cmd_search {
+        let types = [
+            (raw.flag_issues, "issues"),
+            (raw.flag_wiki, "wiki_pages"),
+            (raw.flag_news, "news"),
+            (raw.flag_changesets, "changesets"),
+        ].iter().filter(|&&(wanted, _)| wanted).map(|&(_, name)| name).collect();
+
+        return Ok(Args::Search {
+            terms: raw.arg_terms.connect(" "),
+            project: raw.flag_project,
+            options: SearchOptions {
+                types: types,
+                titles_only: raw.flag_titles_only,
+                open_issues: raw.flag_open_issues,
+            },
+            limit: raw.flag_limit,
+        });
+    }
+
+    if raw.cmd_watching {
+        return Ok(Args::ListWatching { limit: raw.flag_limit });
+    }
+
+    if raw.cmd_projects {
+        return Ok(Args::ListProjects { limit: raw.flag_limit });
+    }
+
+    if raw.cmd_project {
+        if raw.cmd_create {
+            return Ok(Args::CreateProject {
+                name: raw.flag_name.unwrap(),
+                identifier: raw.flag_identifier.unwrap(),
+                description: raw.flag_description,
+                parent: raw.flag_parent_project,
+                is_public: match (raw.flag_private, raw.flag_public) {
+                    (true, _) => Some(false),
+                    (_, true) => Some(true),
+                    _         => None,
+                },
+            });
+        }
+
+        return Ok(Args::ShowProject { project: raw.arg_project.unwrap() });
+    }
+
+    if raw.cmd_versions {
+        return Ok(Args::ListVersions { project: raw.arg_project.unwrap(), limit: raw.flag_limit });
+    }
+
+    if raw.cmd_version {
+        if raw.cmd_rollover {
+            return Ok(Args::RolloverVersion {
+                id: raw.arg_version_id.unwrap(),
+                to: raw.flag_to.unwrap(),
+                close: raw.flag_close,
+                confirmed: raw.flag_yes,
+            });
+        }
+
+        if raw.cmd_show || !(raw.cmd_create || raw.cmd_update || raw.cmd_close) {
+            return Ok(Args::ShowVersion { id: raw.arg_version_id.unwrap() });
+        }
+
+        let status = if raw.cmd_close { Some("closed".to_string()) } else { raw.flag_status };
+
+        if let Some(ref status) = status {
+            if !["open", "locked", "closed"].contains(&&status[..]) {
+                return Err(From::from("version status must be one of: open, locked, closed"));
+            }
+        }
+
+        let attributes = VersionAttributes {
+            name: raw.flag_name,
+            due_date: try!(date_option(raw.flag_due_date, "--due-date")),
+            description: raw.flag_description,
             status: status,
-            assigned_to: raw.flag_assigned_to,
+        };
+
+        if raw.cmd_create {
+            return Ok(Args::CreateVersion { project: raw.arg_project.unwrap(), attributes: attributes });
+        }
+
+        return Ok(Args::UpdateVersion { id: raw.arg_version_id.unwrap(), attributes: attributes });
+    }
+
+    if raw.cmd_wiki {
+        let project = raw.arg_project.unwrap();
+
+        if raw.cmd_sync {
+            let direction = match (raw.flag_push, raw.flag_pull) {
+                (true, _) => SyncDirection::Push,
+                (_, true) => SyncDirection::Pull,
+                _         => SyncDirection::Both,
+            };
+
+            return Ok(Args::SyncWiki { project: project, dir: raw.arg_dir.unwrap(), direction: direction });
+        }
+
+        let page = match raw.arg_page {
+            Some(page) => page,
+            None       => return Ok(Args::ShowWikiIndex { project: project }),
+        };
+
+        if raw.cmd_edit {
+            return Ok(Args::EditWikiPage { project: project, page: page, comment: raw.flag_comment });
+        }
+
+        if raw.cmd_history {
+            return Ok(Args::WikiHistory { project: project, page: page, limit: raw.flag_limit });
+        }
+
+        return Ok(Args::ShowWikiPage { project: project, page: page, version: raw.flag_version });
+    }
+
+    if raw.cmd_changelog {
+        let format = match raw.flag_format.as_ref().map(|f| &f[..]) {
+            None | Some("markdown") => ChangelogFormat::Markdown,
+            Some("text")            => ChangelogFormat::Text,
+            Some("json")            => ChangelogFormat::Json,
+            _                       => return Err(From::from("--format must be one of: markdown, text, json")),
+        };
+
+        return Ok(Args::Changelog {
+            version: raw.arg_version.unwrap(),
+            project: raw.flag_project,
+            format: format,
+        });
+    }
+
+    if raw.cmd_relation {
+        return Ok(Args::DeleteRelation { id: raw.arg_relation_id.unwrap() });
+    }
+
+    if raw.cmd_attachment {
+        return Ok(Args::DownloadAttachment { id: raw.arg_attachment_id.unwrap(), output: raw.flag_output });
+    }
+
+    if raw.cmd_log {
+        let attributes = TimeEntryAttributes {
+            issue: raw.arg_issue_number,
+            hours: raw.arg_hours,
+            activity: raw.flag_activity,
+            date: try!(date_option(raw.flag_date, "--date")),
+            comment: raw.flag_comment,
+        };
+
+        return Ok(Args::LogTime { attributes: attributes });
+    }
+
+    if raw.cmd_timesheet {
+        return Ok(Args::Timesheet { week: raw.flag_week, user: raw.flag_user, edit: raw.flag_edit });
+    }
+
+    if raw.cmd_timer {
+        return Ok(if raw.cmd_start {
+            Args::StartTimer {
+                number: raw.arg_issue_number.unwrap(),
+                activity: raw.flag_activity,
+                comment: raw.flag_comment,
+            }
+        } else if raw.cmd_stop {
+            Args::StopTimer { activity: raw.flag_activity, comment: raw.flag_comment }
+        } else if raw.cmd_status {
+            Args::TimerStatus
+        } else {
+            Args::DiscardTimer
         });
     }
 
+    if raw.cmd_time {
+        if raw.cmd_list {
+            return Ok(Args::ListTimeEntries {
+                user: raw.flag_user,
+                issue: raw.flag_issue,
+                from: try!(date_option(raw.flag_from, "--from")),
+                to: try!(date_option(raw.flag_to, "--to")),
+                limit: raw.flag_limit,
+            });
+        }
+
+        if raw.cmd_import {
+            let format = match raw.flag_format.as_ref().map(|f| &f[..]) {
+                Some("csv")   => ImportFormat::Csv,
+                Some("timew") => ImportFormat::Timewarrior,
+                _             => return Err(From::from("--format must be either 'csv' or 'timew'")),
+            };
+
+            return Ok(Args::ImportTime {
+                path: raw.arg_file.remove(0),
+                format: format,
+                confirmed: raw.flag_yes,
+            });
+        }
+
+        let id = raw.arg_entry_id.unwrap();
+
+        if raw.cmd_delete {
+            return Ok(Args::DeleteTimeEntry { id: id });
+        }
+
+        let attributes = TimeEntryAttributes {
+            issue: raw.flag_issue,
+            hours: raw.flag_hours,
+            activity: raw.flag_activity,
+            date: try!(date_option(raw.flag_date, "--date")),
+            comment: raw.flag_comment,
+        };
+
+        return Ok(Args::EditTimeEntry { id: id, attributes: attributes });
+    }
+
+    if raw.cmd_create {
+        return Ok(Args::CreateIssue(try!(issue_attributes(raw, custom_fields))));
+    }
+
     let issue_number = raw.arg_issue_number.unwrap();
+    let private_notes = raw.flag_private_notes;
+
+    let notes = match (raw.flag_notes.take(), raw.flag_notes_file.take()) {
+        (Some(text), _) => NotesSource::Text(text),
+        (_, Some(path)) => NotesSource::File(path),
+        _               => NotesSource::Editor,
+    };
+
+    if raw.cmd_show {
+        Ok(Args::ShowIssue { number: issue_number })
+    } else if raw.cmd_relations {
+        Ok(Args::ListRelations { number: issue_number })
+    } else if raw.cmd_watch {
+        Ok(Args::WatchIssue { number: issue_number, user: raw.flag_user })
+    } else if raw.cmd_unwatch {
+        Ok(Args::UnwatchIssue { number: issue_number, user: raw.flag_user })
+    } else if raw.cmd_attach {
+        Ok(Args::AttachFiles { number: issue_number, paths: raw.arg_file })
+    } else if raw.cmd_relate {
+        let relation_type = raw.arg_relation_type.unwrap();
 
-    if raw.cmd_update {
-        match raw.flag_status {
-            Some(st) => Ok(Args::UpdateIssue { number: issue_number, new_status: st }),
-            None     => Err(From::from("update")),
+        if !RELATION_TYPES.contains(&&relation_type[..]) {
+            return Err(From::from("unknown relation type"));
         }
+
+        if raw.flag_delay.is_some() && relation_type != "precedes" && relation_type != "follows" {
+            return Err(From::from("--delay is only valid for 'precedes' and 'follows' relations"));
+        }
+
+        Ok(Args::CreateRelation {
+            number: issue_number,
+            relation_type: relation_type,
+            other: raw.arg_other_issue.unwrap(),
+            delay: raw.flag_delay,
+        })
+    } else if raw.cmd_update {
+        Ok(Args::UpdateIssue {
+            number: issue_number,
+            attributes: try!(issue_attributes(raw, custom_fields)),
+            notes: notes,
+            private_notes: private_notes,
+        })
     } else if raw.cmd_close {
-        Ok(Args::CloseIssue { number: issue_number, close_status: raw.flag_status })
+        Ok(Args::CloseIssue {
+            number: issue_number,
+            close_status: raw.flag_status,
+            notes: notes,
+            private_notes: private_notes,
+        })
     } else {
         unreachable!();
     }
 }
+
+/// Whether `date` is a day of the calendar, written as YYYY-MM-DD
+pub fn is_date(date: &str) -> bool {
+    let parts: Vec<&str> = date.split('-').collect();
+
+    let well_formed = parts.len() == 3 && parts[0].len() == 4 && parts[1].len() == 2 && parts[2].len() == 2 &&
+        parts.iter().all(|part| part.chars().all(|chr| chr.is_digit(10)));
+
+    if !well_formed {
+        return false;
+    }
+
+    let year: u32  = parts[0].parse().unwrap();
+    let month: u32 = parts[1].parse().unwrap();
+    let day: u32   = parts[2].parse().unwrap();
+
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+
+    let days_in_month = match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11              => 30,
+        2 if leap                   => 29,
+        2                           => 28,
+        _                           => return false,
+    };
+
+    day >= 1 && day <= days_in_month
+}
+
+/// Passes a date option through, as long as it's given as YYYY-MM-DD
+fn date_option(value: Option<String>, option: &str) -> Result<Option<String>, Error> {
+    match value {
+        Some(ref date) if !is_date(date) => Err(From::from(format!("{} must be given as YYYY-MM-DD", option))),
+        value => Ok(value),
+    }
+}
+
+fn issue_attributes(raw: RawArgs, custom_fields: Vec<(String, String)>) -> Result<IssueAttributes, Error> {
+    Ok(IssueAttributes {
+        project: raw.flag_project,
+        status: raw.flag_status,
+        subject: raw.flag_subject,
+        description: raw.flag_description,
+        tracker: raw.flag_tracker,
+        priority: raw.flag_priority,
+        assigned_to: raw.flag_assigned_to,
+        parent: raw.flag_parent,
+        start_date: try!(date_option(raw.flag_start_date, "--start-date")),
+        due_date: try!(date_option(raw.flag_due_date, "--due-date")),
+        estimated_hours: raw.flag_estimated_hours,
+        done_ratio: raw.flag_done_ratio,
+        target_version: raw.flag_target_version,
+        category: raw.flag_category,
+        is_private: match (raw.flag_private, raw.flag_public) {
+            (true, _) => Some(true),
+            (_, true) => Some(false),
+            _         => None,
+        },
+        custom_fields: custom_fields,
+    })
+}
+
+fn issue_list_options(raw: &mut RawArgs) -> Result<IssueListOptions, Error> {
+    let columns: Option<Vec<String>> = match raw.flag_columns.take() {
+        Some(columns) => Some(columns.split(',').map(|column| column.trim().to_string()).collect()),
+        None if raw.flag_group_by.is_some() => Some(DEFAULT_ISSUE_COLUMNS.iter().map(|c| c.to_string()).collect()),
+        None => None,
+    };
+
+    for column in columns.iter().flat_map(|columns| columns.iter()).chain(raw.flag_group_by.iter()) {
+        if !ISSUE_COLUMNS.contains(&&column[..]) {
+            return Err(From::from("unknown column; see --columns for the available ones"));
+        }
+    }
+
+    Ok(IssueListOptions {
+        sort: raw.flag_sort.take(),
+        columns: columns,
+        group_by: raw.flag_group_by.take(),
+    })
+}
+
+fn filter(value: Option<String>) -> Result<Option<Filter>, Error> {
+    match value {
+        Some(value) => Ok(Some(try!(Filter::parse(&value)))),
+        None        => Ok(None),
+    }
+}
+
+/// Like `filter`, but every bound must be a YYYY-MM-DD date, and '~' isn't accepted
+fn date_filter(value: Option<String>) -> Result<Option<Filter>, Error> {
+    let filter = try!(filter(value));
+
+    let valid = match filter {
+        Some(Filter::Is(ref date)) | Some(Filter::AtLeast(ref date)) | Some(Filter::AtMost(ref date)) => is_date(date),
+        Some(Filter::Between(ref from, ref to)) => is_date(from) && is_date(to),
+        Some(Filter::Contains(_))               => false,
+        Some(Filter::Any) | Some(Filter::NoValue) | None => true,
+    };
+
+    if !valid {
+        return Err(From::from("date filters take dates as YYYY-MM-DD, e.g. --created-on='>=2015-04-01'"));
+    }
+
+    Ok(filter)
+}
+
+fn parse_custom_values(raw_values: &[String]) -> Result<Vec<(String, String)>, Error> {
+    let mut values = Vec::new();
+
+    for raw_value in raw_values.iter() {
+        match raw_value.find('=') {
+            Some(index) => {
+                values.push((raw_value[..index].to_string(), raw_value[index + 1..].to_string()));
+            },
+
+            None => return Err(From::from("custom field values must be given as <name>=<value>")),
+        }
+    }
+
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Filter, is_date, date_filter};
+
+    fn parse(value: &str) -> Filter {
+        Filter::parse(value).unwrap()
+    }
+
+    #[test]
+    fn filters_are_parsed_by_their_operator() {
+        match parse("*") { Filter::Any => (), other => panic!("unexpected filter: {:?}", other) }
+        match parse("!*") { Filter::NoValue => (), other => panic!("unexpected filter: {:?}", other) }
+
+        match parse(">=2015-04-01") {
+            Filter::AtLeast(ref value) => assert_eq!(value, "2015-04-01"),
+            other => panic!("unexpected filter: {:?}", other),
+        }
+
+        match parse("<=2015-04-30") {
+            Filter::AtMost(ref value) => assert_eq!(value, "2015-04-30"),
+            other => panic!("unexpected filter: {:?}", other),
+        }
+
+        match parse("~crash") {
+            Filter::Contains(ref value) => assert_eq!(value, "crash"),
+            other => panic!("unexpected filter: {:?}", other),
+        }
+
+        match parse("john|jane") {
+            Filter::Is(ref value) => assert_eq!(value, "john|jane"),
+            other => panic!("unexpected filter: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn between_filters_need_both_bounds() {
+        match parse("><2015-04-01|2015-04-30") {
+            Filter::Between(ref from, ref to) => {
+                assert_eq!(from, "2015-04-01");
+                assert_eq!(to, "2015-04-30");
+            },
+            other => panic!("unexpected filter: {:?}", other),
+        }
+
+        assert!(Filter::parse("><2015-04-01").is_err());
+    }
+
+    #[test]
+    fn dates_must_be_on_the_calendar() {
+        assert!(is_date("2015-04-01"));
+        assert!(is_date("2016-02-29"));
+
+        assert!(!is_date("2015-02-29"));
+        assert!(!is_date("2015-13-01"));
+        assert!(!is_date("2015-04-31"));
+        assert!(!is_date("2015-4-1"));
+        assert!(!is_date("01/04/2015"));
+        assert!(!is_date("yesterday"));
+    }
+
+    #[test]
+    fn date_filters_check_every_bound() {
+        assert!(date_filter(Some(">=2015-04-01".to_string())).is_ok());
+        assert!(date_filter(Some("><2015-04-01|2015-04-30".to_string())).is_ok());
+        assert!(date_filter(Some("!*".to_string())).is_ok());
+        assert!(date_filter(None).is_ok());
+
+        assert!(date_filter(Some("><2015-04-01|soon".to_string())).is_err());
+        assert!(date_filter(Some("<=2015-04-31".to_string())).is_err());
+        assert!(date_filter(Some("~2015".to_string())).is_err());
+    }
+}
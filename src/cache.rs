@@ -18,9 +18,10 @@ use std::io::prelude::*;
 use std::fs::OpenOptions;
 use std::io::BufReader;
 use std::path::PathBuf;
+use std::collections::HashMap;
 
 use client::{self, Client};
-use models::{IssueStatus, User};
+use models::{IssueStatus, User, Reference, CustomField, Project, Version, Query};
 
 use rustc_serialize::json;
 use time;
@@ -28,6 +29,9 @@ use time;
 pub struct Cache {
     data: CacheData,
     path: PathBuf,
+
+    /// Whether the server refused to list custom fields. Only kept for the current run.
+    custom_fields_forbidden: bool,
 }
 
 impl Cache {
@@ -56,6 +60,19 @@ impl Cache {
         let mut open_options = OpenOptions::new();
         open_options.read(true).write(true);
 
+        let empty = CacheData {
+            issue_statuses: None,
+            projects: None,
+            time_entry_activities: None,
+            users: None,
+            trackers: None,
+            issue_priorities: None,
+            custom_fields: None,
+            versions: None,
+            issue_categories: None,
+            queries: None,
+        };
+
         let cache_data;
 
         if cache_fresh {
@@ -63,33 +80,156 @@ impl Cache {
 
             let mut cache_content = String::new();
             BufReader::new(file).read_to_string(&mut cache_content).unwrap();
-            cache_data = json::decode(&cache_content).unwrap();
+
+            // A cache written by an older rdm may not match the current format; start over then
+            cache_data = json::decode(&cache_content).unwrap_or(empty);
         } else {
-            cache_data = CacheData {
-                issue_statuses: None,
-                users: None,
-            };
+            cache_data = empty;
         }
 
         Ok(Cache {
             data: cache_data,
             path: cache_path,
+            custom_fields_forbidden: false,
         })
     }
 
     pub fn issue_statuses(&mut self, client: &Client) -> Result<Vec<(u32, String)>, client::Error> {
-        let statuses = match self.data.issue_statuses {
-            Some(ref statuses) => statuses.clone(),
-
-            None => {
-                let statuses = try!(client.issue_statuses());
-                self.data.issue_statuses = Some(statuses.clone());
-                self.update_cache();
-                statuses
+        let statuses = try!(self.cached(|data| &mut data.issue_statuses, || client.issue_statuses()));
+        Ok(statuses.map_in_place(|s| s.into_pair()))
+    }
+
+    pub fn closed_status_ids(&mut self, client: &Client) -> Result<Vec<u32>, client::Error> {
+        let statuses = try!(self.cached(|data| &mut data.issue_statuses, || client.issue_statuses()));
+        Ok(statuses.iter().filter(|s| s.is_closed()).map(|s| s.id()).collect())
+    }
+
+    pub fn users(&mut self, client: &Client) -> Result<Vec<User>, client::Error> {
+        self.cached(|data| &mut data.users, || client.users())
+    }
+
+    pub fn trackers(&mut self, client: &Client) -> Result<Vec<Reference>, client::Error> {
+        self.cached(|data| &mut data.trackers, || client.trackers())
+    }
+
+    pub fn issue_priorities(&mut self, client: &Client) -> Result<Vec<Reference>, client::Error> {
+        self.cached(|data| &mut data.issue_priorities, || client.issue_priorities())
+    }
+
+    pub fn projects(&mut self, client: &Client) -> Result<Vec<Project>, client::Error> {
+        self.cached(|data| &mut data.projects, || client.projects())
+    }
+
+    /// Drops the cached project list, e.g. after creating a project
+    pub fn forget_projects(&mut self) {
+        self.data.projects = None;
+        self.update_cache();
+    }
+
+    pub fn time_entry_activities(&mut self, client: &Client) -> Result<Vec<Reference>, client::Error> {
+        self.cached(|data| &mut data.time_entry_activities, || client.time_entry_activities())
+    }
+
+    /// Only administrators can list custom fields; for everyone else, there are none. The server
+    /// is only asked once per run, rather than once per custom field given.
+    pub fn custom_fields(&mut self, client: &Client) -> Result<Vec<CustomField>, client::Error> {
+        if self.custom_fields_forbidden {
+            return Ok(Vec::new());
+        }
+
+        match self.cached(|data| &mut data.custom_fields, || client.custom_fields()) {
+            Err(client::Error::Forbidden(..)) => {
+                self.custom_fields_forbidden = true;
+                Ok(Vec::new())
+            },
+            result => result,
+        }
+    }
+
+    pub fn versions(&mut self, client: &Client, project: &str) -> Result<Vec<Version>, client::Error> {
+        let key = try!(self.project_key(client, project));
+        self.cached_by_key(&key, |data| &mut data.versions, || client.versions(project))
+    }
+
+    /// Drops the cached versions of `project`, e.g. after creating or renaming one of them
+    pub fn forget_versions(&mut self, client: &Client, project: &str) {
+        let key = self.project_key(client, project).unwrap_or(project.to_string());
+
+        if let Some(ref mut versions) = self.data.versions {
+            versions.remove(&key);
+        }
+
+        self.update_cache();
+    }
+
+    pub fn issue_categories(&mut self, client: &Client, project: &str) -> Result<Vec<Reference>, client::Error> {
+        let key = try!(self.project_key(client, project));
+        self.cached_by_key(&key, |data| &mut data.issue_categories, || client.issue_categories(project))
+    }
+
+    pub fn queries(&mut self, client: &Client) -> Result<Vec<Query>, client::Error> {
+        self.cached(|data| &mut data.queries, || client.queries())
+    }
+
+    /// Drops the cached queries, e.g. when looking for one saved after they were cached
+    pub fn forget_queries(&mut self) {
+        self.data.queries = None;
+        self.update_cache();
+    }
+
+    /// Project lists are kept by project identifier, so that a project given by its numeric id
+    /// shares the entry of the same project given by identifier
+    fn project_key(&mut self, client: &Client, project: &str) -> Result<String, client::Error> {
+        let projects = try!(self.projects(client));
+
+        let identifier = projects.iter()
+            .find(|p| p.id.to_string() == project)
+            .map(|p| p.identifier.clone());
+
+        Ok(identifier.unwrap_or(project.to_string()))
+    }
+
+    fn cached<T, S, F>(&mut self, select: S, fetch: F) -> Result<Vec<T>, client::Error>
+        where T: Clone,
+              S: Fn(&mut CacheData) -> &mut Option<Vec<T>>,
+              F: FnOnce() -> Result<Vec<T>, client::Error>
+    {
+        if let Some(ref items) = *select(&mut self.data) {
+            return Ok(items.clone());
+        }
+
+        let items = try!(fetch());
+        *select(&mut self.data) = Some(items.clone());
+        self.update_cache();
+
+        Ok(items)
+    }
+
+    /// Like `cached`, for lists that belong to something else (e.g. a project's versions)
+    fn cached_by_key<T, S, F>(&mut self, key: &str, select: S, fetch: F) -> Result<Vec<T>, client::Error>
+        where T: Clone,
+              S: Fn(&mut CacheData) -> &mut Option<HashMap<String, Vec<T>>>,
+              F: FnOnce() -> Result<Vec<T>, client::Error>
+    {
+        if let Some(items) = select(&mut self.data).as_ref().and_then(|by_key| by_key.get(key)) {
+            return Ok(items.clone());
+        }
+
+        let items = try!(fetch());
+
+        {
+            let by_key = select(&mut self.data);
+
+            if by_key.is_none() {
+                *by_key = Some(HashMap::new());
             }
-        };
 
-        Ok(statuses.map_in_place(|s| s.into_pair()))
+            by_key.as_mut().unwrap().insert(key.to_string(), items.clone());
+        }
+
+        self.update_cache();
+
+        Ok(items)
     }
 
     fn update_cache(&self) {
@@ -107,5 +247,13 @@ impl Cache {
 #[derive(RustcDecodable, RustcEncodable, Clone)]
 struct CacheData {
     issue_statuses: Option<Vec<IssueStatus>>,
-    users: Option<Vec<User>>
+    projects: Option<Vec<Project>>,
+    time_entry_activities: Option<Vec<Reference>>,
+    users: Option<Vec<User>>,
+    trackers: Option<Vec<Reference>>,
+    issue_priorities: Option<Vec<Reference>>,
+    custom_fields: Option<Vec<CustomField>>,
+    versions: Option<HashMap<String, Vec<Version>>>,
+    issue_categories: Option<HashMap<String, Vec<Reference>>>,
+    queries: Option<Vec<Query>>,
 }
@@ -0,0 +1,236 @@
+/* rdm - A command-line redmine client
+ * Copyright (C) 2015 Renato Zannon
+ *
+ * This program is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation; either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program; if not, see <http://www.gnu.org/licenses/>. */
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::iter;
+
+use rustc_serialize::json::{Json, ToJson};
+use url::UrlParser;
+
+use args::ChangelogFormat;
+use models::{Issue, Version};
+use user_config::Config;
+
+/// The issues of one tracker, under the heading configured for it
+struct Section<'a> {
+    tracker_id: u32,
+    tracker: &'a str,
+    heading: String,
+    issues: Vec<&'a Issue>,
+}
+
+pub fn render(version: &Version, issues: &[Issue], config: &Config, format: ChangelogFormat) -> String {
+    let title = expand_title(config.changelog_title(), version);
+    let sections = sections(issues, config);
+
+    match format {
+        ChangelogFormat::Markdown => markdown(&title, &sections, config),
+        ChangelogFormat::Text     => text(&title, &sections, config),
+        ChangelogFormat::Json     => format!("{}\n", json(&title, version, &sections, config).pretty()),
+    }
+}
+
+fn sections<'a>(issues: &'a [Issue], config: &Config) -> Vec<Section<'a>> {
+    let mut sections: Vec<Section> = Vec::new();
+
+    for issue in issues.iter() {
+        if let Some(section) = sections.iter_mut().find(|s| s.tracker_id == issue.tracker.id) {
+            section.issues.push(issue);
+            continue;
+        }
+
+        sections.push(Section {
+            tracker_id: issue.tracker.id,
+            tracker: &issue.tracker.name,
+            heading: config.changelog_heading(&issue.tracker.name).to_string(),
+            issues: vec![issue],
+        });
+    }
+
+    sections.sort_by(|a, b| a.tracker_id.cmp(&b.tracker_id));
+
+    for section in sections.iter_mut() {
+        section.issues.sort_by(|a, b| a.id.cmp(&b.id));
+    }
+
+    sections
+}
+
+/// Expands `{version}`, `{project}` and `{date}` on a title template
+fn expand_title(template: &str, version: &Version) -> String {
+    let date = version.due_date.as_ref().map(|d| &d[..]).unwrap_or("unreleased");
+
+    template
+        .replace("{version}", &version.name)
+        .replace("{project}", &version.project.name)
+        .replace("{date}", date)
+}
+
+fn markdown(title: &str, sections: &[Section], config: &Config) -> String {
+    let mut out = String::new();
+
+    writeln!(out, "# {}", title).unwrap();
+
+    for section in sections.iter() {
+        writeln!(out, "\n## {}\n", section.heading).unwrap();
+
+        for issue in section.issues.iter() {
+            writeln!(out, "- {} ([#{}]({}))", issue.subject, issue.id, issue_url(config, issue.id)).unwrap();
+        }
+    }
+
+    out
+}
+
+fn text(title: &str, sections: &[Section], config: &Config) -> String {
+    let mut out = String::new();
+
+    writeln!(out, "{}\n{}", title, underline(title, "=")).unwrap();
+
+    for section in sections.iter() {
+        writeln!(out, "\n{}\n{}", section.heading, underline(&section.heading, "-")).unwrap();
+
+        for issue in section.issues.iter() {
+            writeln!(out, "  * #{} {}", issue.id, issue.subject).unwrap();
+            writeln!(out, "    {}", issue_url(config, issue.id)).unwrap();
+        }
+    }
+
+    out
+}
+
+fn json(title: &str, version: &Version, sections: &[Section], config: &Config) -> Json {
+    let mut version_json = BTreeMap::new();
+    version_json.insert("id".to_string(), version.id.to_json());
+    version_json.insert("name".to_string(), version.name.to_json());
+    version_json.insert("due_date".to_string(), version.due_date.to_json());
+
+    let sections_json = sections.iter().map(|section| {
+        let issues = section.issues.iter().map(|issue| {
+            let mut issue_json = BTreeMap::new();
+            issue_json.insert("id".to_string(), issue.id.to_json());
+            issue_json.insert("subject".to_string(), issue.subject.to_json());
+            issue_json.insert("url".to_string(), issue_url(config, issue.id).to_json());
+
+            Json::Object(issue_json)
+        }).collect();
+
+        let mut section_json = BTreeMap::new();
+        section_json.insert("tracker".to_string(), section.tracker.to_json());
+        section_json.insert("heading".to_string(), section.heading.to_json());
+        section_json.insert("issues".to_string(), Json::Array(issues));
+
+        Json::Object(section_json)
+    }).collect();
+
+    let mut root = BTreeMap::new();
+    root.insert("title".to_string(), title.to_json());
+    root.insert("version".to_string(), Json::Object(version_json));
+    root.insert("sections".to_string(), Json::Array(sections_json));
+
+    Json::Object(root)
+}
+
+fn issue_url(config: &Config, id: u32) -> String {
+    UrlParser::new()
+        .base_url(config.redmine_url())
+        .parse(&format!("issues/{}", id))
+        .unwrap()
+        .to_string()
+}
+
+fn underline(text: &str, ch: &str) -> String {
+    iter::repeat(ch).take(text.chars().count()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use rustc_serialize::json;
+
+    use args::ChangelogFormat;
+    use models::{Issue, Version};
+    use models::fixtures::issue;
+    use user_config::Config;
+    use super::{render, expand_title};
+
+    fn config() -> Config {
+        Config::from_json(r#"{
+            "redmine_key": "key",
+            "redmine_url": "https://redmine.example.com/",
+            "changelog_headings": { "Bug": "Fixes" }
+        }"#)
+    }
+
+    fn version(due_date: Option<&str>) -> Version {
+        let due_date = due_date.map(|date| format!("\"{}\"", date)).unwrap_or("null".to_string());
+
+        json::decode(&format!(r#"{{
+            "id": 4, "project": {{ "id": 1, "name": "rdm" }}, "name": "1.2", "status": "open",
+            "due_date": {}, "created_on": "2015-04-01T10:00:00Z", "updated_on": "2015-04-01T10:00:00Z"
+        }}"#, due_date)).unwrap()
+    }
+
+    fn issues() -> Vec<Issue> {
+        vec![issue(7, (2, "Feature"), "Closed", "Add X"), issue(5, (1, "Bug"), "Closed", "Crash"),
+             issue(3, (1, "Bug"), "Closed", "Leak")]
+    }
+
+    #[test]
+    fn titles_are_expanded() {
+        assert_eq!(expand_title("{project} {version} ({date})", &version(Some("2015-05-01"))), "rdm 1.2 (2015-05-01)");
+        assert_eq!(expand_title("{version} ({date})", &version(None)), "1.2 (unreleased)");
+    }
+
+    #[test]
+    fn markdown_groups_issues_by_tracker() {
+        let changelog = render(&version(Some("2015-05-01")), &issues(), &config(), ChangelogFormat::Markdown);
+
+        assert_eq!(changelog, "# 1.2 (2015-05-01)\n\
+                               \n\
+                               ## Fixes\n\
+                               \n\
+                               - Leak ([#3](https://redmine.example.com/issues/3))\n\
+                               - Crash ([#5](https://redmine.example.com/issues/5))\n\
+                               \n\
+                               ## Feature\n\
+                               \n\
+                               - Add X ([#7](https://redmine.example.com/issues/7))\n");
+    }
+
+    #[test]
+    fn text_underlines_headings() {
+        let changelog = render(&version(Some("2015-05-01")), &issues(), &config(), ChangelogFormat::Text);
+        let lines: Vec<&str> = changelog.lines().collect();
+
+        assert_eq!(&lines[..6], &["1.2 (2015-05-01)", "================", "", "Fixes", "-----", "  * #3 Leak"]);
+        assert_eq!(lines[6], "    https://redmine.example.com/issues/3");
+    }
+
+    #[test]
+    fn json_lists_sections_in_tracker_order() {
+        let changelog = render(&version(None), &issues(), &config(), ChangelogFormat::Json);
+        let parsed = json::Json::from_str(&changelog).unwrap();
+
+        let sections = parsed.find("sections").and_then(|sections| sections.as_array()).unwrap();
+        let headings: Vec<&str> = sections.iter()
+            .map(|section| section.find("heading").and_then(|heading| heading.as_string()).unwrap())
+            .collect();
+
+        assert_eq!(headings, vec!["Fixes", "Feature"]);
+        assert_eq!(parsed.find("title").and_then(|title| title.as_string()), Some("1.2 (unreleased)"));
+    }
+}
@@ -14,35 +14,80 @@
  * You should have received a copy of the GNU General Public License
  * along with this program; if not, see <http://www.gnu.org/licenses/>. */
 
-use std::fmt;
+use std::{cmp, fmt};
 use std::io::{self, Read};
-use std::collections::HashMap;
-use url::{Url, UrlParser};
+use url::{Url, UrlParser, ParseError};
+use url::percent_encoding::{utf8_percent_encode, DEFAULT_ENCODE_SET};
 use uuid::Uuid;
 
 use hyper;
 use hyper::header;
 use hyper::status::{StatusCode, StatusClass};
 
-use rustc_serialize::json;
+use rustc_serialize::Decodable;
+use rustc_serialize::json::{self, Json, ToJson};
 
 use user_config::Config;
-use models::{User, IssueStatus};
+use models::{User, IssueStatus, Issue, Reference, CustomField, Relation, Attachment, TimeEntry,
+             Project, Membership, Version, WikiPage, WikiPageSummary, SearchResult, Query};
 
 header! {
     (RedmineApiKey, "X-Redmine-API-Key") => [String]
 }
 
+/// Attributes to be sent when creating or updating an object (an issue, a time entry, etc).
+/// Only the attributes that were set end up on the request.
+pub struct Fields {
+    root: &'static str,
+    fields: json::Object,
+}
+
+impl Fields {
+    pub fn issue() -> Fields { Fields::new("issue") }
+    pub fn time_entry() -> Fields { Fields::new("time_entry") }
+    pub fn project() -> Fields { Fields::new("project") }
+    pub fn version() -> Fields { Fields::new("version") }
+    pub fn wiki_page() -> Fields { Fields::new("wiki_page") }
+
+    fn new(root: &'static str) -> Fields {
+        Fields { root: root, fields: json::Object::new() }
+    }
+
+    pub fn set<T: ToJson + ?Sized>(&mut self, name: &str, value: &T) -> &mut Fields {
+        self.fields.insert(name.to_string(), value.to_json());
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    fn to_body(&self) -> String {
+        let mut body = json::Object::new();
+        body.insert(self.root.to_string(), Json::Object(self.fields.clone()));
+
+        Json::Object(body).to_string()
+    }
+}
+
+/// The largest page size Redmine accepts on list endpoints
+const MAX_PAGE_SIZE: u32 = 100;
+
 pub struct Client {
     config: Config,
 }
 
 struct Request {
     method: Method,
-    body: Option<String>,
+    body: Option<Body>,
     url: Url,
 }
 
+enum Body {
+    Json(String),
+    Binary(Vec<u8>),
+}
+
 #[derive(Clone, Copy, Debug)]
 enum Method {
     Get,
@@ -75,6 +120,9 @@ pub enum Error {
     Response(Box<PrintableError>),
     Forbidden(Method, Url),
     Server(Method, Url),
+    Validation(Vec<String>),
+    Conflict(Method, Url),
+    ForeignHost(Url),
     Unknown(Method, Url, StatusCode),
 }
 
@@ -92,6 +140,18 @@ impl fmt::Display for Error {
                 write!(f, "Server-side error: Server returned error on {} {}", method, url)
             },
 
+            Error::Validation(ref messages) => {
+                write!(f, "Rejected by the server: {}", messages.connect("; "))
+            },
+
+            Error::Conflict(method, ref url) => {
+                write!(f, "Conflict: {} {} was refused because it changed on the server meanwhile", method, url)
+            },
+
+            Error::ForeignHost(ref url) => {
+                write!(f, "Refusing to send the API key to {}, which is not on the configured Redmine server", url)
+            },
+
             Error::Unknown(method, ref url, ref status) => {
                 write!(f, "Unkwnown error: Server returned {} on {} {}", status, method, url)
             }
@@ -106,6 +166,9 @@ impl ::std::error::Error for Error {
             Error::Response(_)      => "Received invalid response",
             Error::Forbidden(_, _)  => "User not authorized to perform action",
             Error::Server(_, _)     => "Server-side error",
+            Error::Validation(_)    => "Server rejected the submitted data",
+            Error::Conflict(_, _)   => "Resource changed on the server",
+            Error::ForeignHost(_)   => "Request to a server other than the configured one",
             Error::Unknown(_, _, _) => "Unknown error",
         }
     }
@@ -123,6 +186,18 @@ impl From<io::Error> for Error {
     }
 }
 
+impl From<ParseError> for Error {
+    fn from(err: ParseError) -> Error {
+        Error::Response(Box::new(err))
+    }
+}
+
+impl From<json::ParserError> for Error {
+    fn from(err: json::ParserError) -> Error {
+        Error::Response(Box::new(err))
+    }
+}
+
 impl From<json::DecoderError> for Error {
     fn from(err: json::DecoderError) -> Error {
         Error::Response(Box::new(err))
@@ -136,64 +211,470 @@ impl Client {
         Client { config: config }
     }
 
-    pub fn update_issue(&self, number: u32, status: u32) -> Result<(), Error> {
-        let body = {
-            let mut body       = HashMap::new();
-            let mut issue_diff = HashMap::new();
-
-            issue_diff.insert("status_id", status);
-            body.insert("issue", issue_diff);
-
-            json::encode(&body).unwrap()
-        };
-
+    pub fn update_issue(&self, number: u32, fields: &Fields) -> Result<(), Error> {
         let _response = try!(self.send_request(Request {
             method: Method::Put,
-            body: Some(body),
+            body: Some(Body::Json(fields.to_body())),
             url: self.issue_url(number),
         }));
 
         Ok(())
     }
 
-    pub fn issue_statuses(&self) -> Result<Vec<IssueStatus>, Error> {
-        #[derive(RustcDecodable, Debug)]
-        struct IssueStatuses {
-            issue_statuses: Vec<IssueStatus>
+    /// Creates an issue on `project`, returning its number
+    pub fn create_issue(&self, project: &str, fields: &Fields) -> Result<u32, Error> {
+        #[derive(RustcDecodable)]
+        struct CreatedIssue {
+            id: u32
+        }
+
+        #[derive(RustcDecodable)]
+        struct Created {
+            issue: CreatedIssue
+        }
+
+        let url = self.build_url(&format!("projects/{}/issues.json", project));
+
+        let response = try!(self.request_json(Method::Post, url, Some(fields.to_body())));
+        let parsed: Created = try!(Decodable::decode(&mut json::Decoder::new(response)));
+
+        Ok(parsed.issue.id)
+    }
+
+    /// Uploads a file, returning the token that attaches it to an issue on a later update
+    pub fn upload(&self, contents: Vec<u8>) -> Result<String, Error> {
+        #[derive(RustcDecodable)]
+        struct Upload {
+            token: String
+        }
+
+        #[derive(RustcDecodable)]
+        struct Uploaded {
+            upload: Upload
         }
 
         let mut response = try!(self.send_request(Request {
-            method: Method::Get,
-            body: None,
-            url: self.build_url("issue_statuses.json"),
+            method: Method::Post,
+            body: Some(Body::Binary(contents)),
+            url: self.build_url("uploads.json"),
         }));
 
         let mut response_contents = String::new();
         try!(response.read_to_string(&mut response_contents));
 
-        let parsed: IssueStatuses = try!(json::decode(&response_contents));
+        let parsed: Uploaded = try!(json::decode(&response_contents));
 
-        Ok(parsed.issue_statuses)
+        Ok(parsed.upload.token)
     }
 
-    pub fn users(&self) -> Result<Vec<User>, Error> {
-        #[derive(RustcDecodable, Debug)]
-        struct Users {
-            users: Vec<User>
+    pub fn attachment(&self, id: u32) -> Result<Attachment, Error> {
+        #[derive(RustcDecodable)]
+        struct SingleAttachment {
+            attachment: Attachment
+        }
+
+        let response = try!(self.get_json(self.build_url(&format!("attachments/{}.json", id))));
+        let parsed: SingleAttachment = try!(Decodable::decode(&mut json::Decoder::new(response)));
+
+        Ok(parsed.attachment)
+    }
+
+    pub fn download(&self, attachment: &Attachment) -> Result<Vec<u8>, Error> {
+        let redmine_url = self.config.redmine_url();
+
+        let url = try!(UrlParser::new()
+            .base_url(redmine_url)
+            .parse(&attachment.content_url));
+
+        // Every request carries the API key, so it must not go anywhere but the Redmine server
+        let same_server = url.scheme == redmine_url.scheme
+            && url.host() == redmine_url.host()
+            && url.port_or_default() == redmine_url.port_or_default();
+
+        if !same_server {
+            return Err(Error::ForeignHost(url));
         }
 
         let mut response = try!(self.send_request(Request {
             method: Method::Get,
             body: None,
-            url: self.build_url("users.json"),
+            url: url,
+        }));
+
+        let mut contents = Vec::new();
+        try!(response.read_to_end(&mut contents));
+
+        Ok(contents)
+    }
+
+    pub fn projects(&self) -> Result<Vec<Project>, Error> {
+        self.get_all("projects.json", "projects", &[], None)
+    }
+
+    pub fn project(&self, project: &str) -> Result<Project, Error> {
+        #[derive(RustcDecodable)]
+        struct SingleProject {
+            project: Project
+        }
+
+        let mut url = self.build_url(&format!("projects/{}.json", project));
+        url.set_query_from_pairs(vec![("include", "trackers,issue_categories,enabled_modules")].into_iter());
+
+        let response = try!(self.get_json(url));
+        let parsed: SingleProject = try!(Decodable::decode(&mut json::Decoder::new(response)));
+
+        Ok(parsed.project)
+    }
+
+    pub fn memberships(&self, project: &str) -> Result<Vec<Membership>, Error> {
+        self.get_all(&format!("projects/{}/memberships.json", project), "memberships", &[], None)
+    }
+
+    pub fn create_project(&self, fields: &Fields) -> Result<Project, Error> {
+        #[derive(RustcDecodable)]
+        struct Created {
+            project: Project
+        }
+
+        let url = self.build_url("projects.json");
+        let response = try!(self.request_json(Method::Post, url, Some(fields.to_body())));
+        let parsed: Created = try!(Decodable::decode(&mut json::Decoder::new(response)));
+
+        Ok(parsed.project)
+    }
+
+    pub fn time_entry_activities(&self) -> Result<Vec<Reference>, Error> {
+        self.get_all("enumerations/time_entry_activities.json", "time_entry_activities", &[], None)
+    }
+
+    pub fn time_entries(&self, params: &[(String, String)], limit: Option<u32>) -> Result<Vec<TimeEntry>, Error> {
+        self.get_all("time_entries.json", "time_entries", params, limit)
+    }
+
+    pub fn create_time_entry(&self, fields: &Fields) -> Result<TimeEntry, Error> {
+        #[derive(RustcDecodable)]
+        struct Created {
+            time_entry: TimeEntry
+        }
+
+        let url = self.build_url("time_entries.json");
+        let response = try!(self.request_json(Method::Post, url, Some(fields.to_body())));
+        let parsed: Created = try!(Decodable::decode(&mut json::Decoder::new(response)));
+
+        Ok(parsed.time_entry)
+    }
+
+    pub fn update_time_entry(&self, id: u32, fields: &Fields) -> Result<(), Error> {
+        let _response = try!(self.send_request(Request {
+            method: Method::Put,
+            body: Some(Body::Json(fields.to_body())),
+            url: self.build_url(&format!("time_entries/{}.json", id)),
+        }));
+
+        Ok(())
+    }
+
+    pub fn delete_time_entry(&self, id: u32) -> Result<(), Error> {
+        let _response = try!(self.send_request(Request {
+            method: Method::Delete,
+            body: None,
+            url: self.build_url(&format!("time_entries/{}.json", id)),
+        }));
+
+        Ok(())
+    }
+
+    pub fn relations(&self, number: u32) -> Result<Vec<Relation>, Error> {
+        self.get_all(&format!("issues/{}/relations.json", number), "relations", &[], None)
+    }
+
+    pub fn create_relation(&self, number: u32, other: u32, relation_type: &str, delay: Option<i32>)
+        -> Result<Relation, Error>
+    {
+        #[derive(RustcDecodable)]
+        struct Created {
+            relation: Relation
+        }
+
+        let mut relation = json::Object::new();
+        relation.insert("issue_to_id".to_string(), other.to_json());
+        relation.insert("relation_type".to_string(), relation_type.to_json());
+
+        if let Some(delay) = delay {
+            relation.insert("delay".to_string(), delay.to_json());
+        }
+
+        let mut body = json::Object::new();
+        body.insert("relation".to_string(), Json::Object(relation));
+
+        let url = self.build_url(&format!("issues/{}/relations.json", number));
+        let response = try!(self.request_json(Method::Post, url, Some(Json::Object(body).to_string())));
+        let parsed: Created = try!(Decodable::decode(&mut json::Decoder::new(response)));
+
+        Ok(parsed.relation)
+    }
+
+    pub fn delete_relation(&self, id: u32) -> Result<(), Error> {
+        let _response = try!(self.send_request(Request {
+            method: Method::Delete,
+            body: None,
+            url: self.build_url(&format!("relations/{}.json", id)),
+        }));
+
+        Ok(())
+    }
+
+    pub fn add_watcher(&self, number: u32, user_id: u32) -> Result<(), Error> {
+        let mut body = json::Object::new();
+        body.insert("user_id".to_string(), user_id.to_json());
+
+        let _response = try!(self.send_request(Request {
+            method: Method::Post,
+            body: Some(Body::Json(Json::Object(body).to_string())),
+            url: self.build_url(&format!("issues/{}/watchers.json", number)),
+        }));
+
+        Ok(())
+    }
+
+    pub fn remove_watcher(&self, number: u32, user_id: u32) -> Result<(), Error> {
+        let _response = try!(self.send_request(Request {
+            method: Method::Delete,
+            body: None,
+            url: self.build_url(&format!("issues/{}/watchers/{}.json", number, user_id)),
+        }));
+
+        Ok(())
+    }
+
+    pub fn wiki_pages(&self, project: &str) -> Result<Vec<WikiPageSummary>, Error> {
+        self.get_all(&format!("projects/{}/wiki/index.json", project), "wiki_pages", &[], None)
+    }
+
+    /// Fetches a wiki page; its latest version unless `version` is given
+    pub fn wiki_page(&self, project: &str, title: &str, version: Option<u32>) -> Result<WikiPage, Error> {
+        #[derive(RustcDecodable)]
+        struct SingleWikiPage {
+            wiki_page: WikiPage
+        }
+
+        let response = try!(self.get_json(self.wiki_page_url(project, title, version)));
+        let parsed: SingleWikiPage = try!(Decodable::decode(&mut json::Decoder::new(response)));
+
+        Ok(parsed.wiki_page)
+    }
+
+    /// Creates or updates a wiki page. When the fields carry the `version` that was edited,
+    /// Redmine refuses the update (with `Error::Conflict`) if the page changed since.
+    pub fn update_wiki_page(&self, project: &str, title: &str, fields: &Fields) -> Result<(), Error> {
+        let _response = try!(self.send_request(Request {
+            method: Method::Put,
+            body: Some(Body::Json(fields.to_body())),
+            url: self.wiki_page_url(project, title, None),
+        }));
+
+        Ok(())
+    }
+
+    pub fn versions(&self, project: &str) -> Result<Vec<Version>, Error> {
+        self.get_all(&format!("projects/{}/versions.json", project), "versions", &[], None)
+    }
+
+    pub fn version(&self, id: u32) -> Result<Version, Error> {
+        #[derive(RustcDecodable)]
+        struct SingleVersion {
+            version: Version
+        }
+
+        let response = try!(self.get_json(self.build_url(&format!("versions/{}.json", id))));
+        let parsed: SingleVersion = try!(Decodable::decode(&mut json::Decoder::new(response)));
+
+        Ok(parsed.version)
+    }
+
+    pub fn create_version(&self, project: &str, fields: &Fields) -> Result<Version, Error> {
+        #[derive(RustcDecodable)]
+        struct Created {
+            version: Version
+        }
+
+        let url = self.build_url(&format!("projects/{}/versions.json", project));
+        let response = try!(self.request_json(Method::Post, url, Some(fields.to_body())));
+        let parsed: Created = try!(Decodable::decode(&mut json::Decoder::new(response)));
+
+        Ok(parsed.version)
+    }
+
+    pub fn update_version(&self, id: u32, fields: &Fields) -> Result<(), Error> {
+        let _response = try!(self.send_request(Request {
+            method: Method::Put,
+            body: Some(Body::Json(fields.to_body())),
+            url: self.build_url(&format!("versions/{}.json", id)),
+        }));
+
+        Ok(())
+    }
+
+    pub fn issue_categories(&self, project: &str) -> Result<Vec<Reference>, Error> {
+        self.get_all(&format!("projects/{}/issue_categories.json", project), "issue_categories", &[], None)
+    }
+
+    /// Lists the custom field definitions. Only available to administrators.
+    pub fn custom_fields(&self) -> Result<Vec<CustomField>, Error> {
+        self.get_all("custom_fields.json", "custom_fields", &[], None)
+    }
+
+    pub fn issue_priorities(&self) -> Result<Vec<Reference>, Error> {
+        self.get_all("enumerations/issue_priorities.json", "issue_priorities", &[], None)
+    }
+
+    pub fn issue_statuses(&self) -> Result<Vec<IssueStatus>, Error> {
+        self.get_all("issue_statuses.json", "issue_statuses", &[], None)
+    }
+
+    pub fn users(&self) -> Result<Vec<User>, Error> {
+        self.get_all("users.json", "users", &[], None)
+    }
+
+    pub fn trackers(&self) -> Result<Vec<Reference>, Error> {
+        self.get_all("trackers.json", "trackers", &[], None)
+    }
+
+    pub fn issue(&self, number: u32) -> Result<Issue, Error> {
+        #[derive(RustcDecodable, Debug)]
+        struct SingleIssue {
+            issue: Issue
+        }
+
+        let mut url = self.issue_url(number);
+        url.set_query_from_pairs(vec![
+            ("include", "journals,attachments,relations,children,watchers,changesets")
+        ].into_iter());
+
+        let mut response = try!(self.get_json(url));
+        normalize_custom_values(&mut response);
+
+        let parsed: SingleIssue = try!(Decodable::decode(&mut json::Decoder::new(response)));
+
+        Ok(parsed.issue)
+    }
+
+    pub fn current_user(&self) -> Result<User, Error> {
+        #[derive(RustcDecodable, Debug)]
+        struct CurrentUser {
+            user: User
+        }
+
+        let response = try!(self.get_json(self.build_url("users/current.json")));
+        let parsed: CurrentUser = try!(Decodable::decode(&mut json::Decoder::new(response)));
+
+        Ok(parsed.user)
+    }
+
+    pub fn issues(&self, params: &[(String, String)], limit: Option<u32>) -> Result<Vec<Issue>, Error> {
+        self.get_all_with("issues.json", "issues", params, limit, normalize_custom_values)
+    }
+
+    pub fn queries(&self) -> Result<Vec<Query>, Error> {
+        self.get_all("queries.json", "queries", &[], None)
+    }
+
+    /// Searches everything visible to the user, or only `project` (and its subprojects)
+    pub fn search(&self, project: Option<&str>, params: &[(String, String)], limit: Option<u32>)
+        -> Result<Vec<SearchResult>, Error>
+    {
+        let path = match project {
+            Some(project) => format!("projects/{}/search.json", project),
+            None          => "search.json".to_string(),
+        };
+
+        self.get_all(&path, "results", params, limit)
+    }
+
+    /// Counts the issues matching `params`, without fetching them
+    pub fn issue_count(&self, params: &[(String, String)]) -> Result<u64, Error> {
+        let mut count_params = params.to_vec();
+        count_params.push(("limit".to_string(), "1".to_string()));
+
+        let mut url = self.build_url("issues.json");
+        url.set_query_from_pairs(count_params.iter().map(|&(ref k, ref v)| (&k[..], &v[..])));
+
+        let response = try!(self.get_json(url));
+
+        match response.find("total_count").and_then(|count| count.as_u64()) {
+            Some(count) => Ok(count),
+            None => Err(From::from(json::DecoderError::MissingFieldError("total_count".to_string()))),
+        }
+    }
+
+    /// Fetches every item of a Redmine list endpoint, following `offset`/`limit` until
+    /// `total_count` items were received (or until `max_items`, if given). Endpoints that aren't
+    /// paginated (no `total_count` on the response) are fetched with a single request.
+    fn get_all<T: Decodable>(&self, path: &str, key: &str, params: &[(String, String)],
+                             max_items: Option<u32>) -> Result<Vec<T>, Error> {
+        self.get_all_with(path, key, params, max_items, |_| ())
+    }
+
+    /// Like `get_all`, but lets `prepare` adjust each page before it's decoded
+    fn get_all_with<T, F>(&self, path: &str, key: &str, params: &[(String, String)],
+                          max_items: Option<u32>, prepare: F) -> Result<Vec<T>, Error>
+        where T: Decodable, F: Fn(&mut Json)
+    {
+        let mut items: Vec<T> = Vec::new();
+
+        loop {
+            let remaining = max_items.map(|max| max - items.len() as u32);
+            let page_size = match remaining {
+                Some(0)         => return Ok(items),
+                Some(remaining) => cmp::min(remaining, MAX_PAGE_SIZE),
+                None            => MAX_PAGE_SIZE,
+            };
+
+            let mut page_params = params.to_vec();
+            page_params.push(("offset".to_string(), items.len().to_string()));
+            page_params.push(("limit".to_string(), page_size.to_string()));
+
+            let mut url = self.build_url(path);
+            url.set_query_from_pairs(page_params.iter().map(|&(ref k, ref v)| (&k[..], &v[..])));
+
+            let mut response = try!(self.get_json(url));
+            prepare(&mut response);
+
+            let total_count = response.find("total_count").and_then(|count| count.as_u64());
+
+            let page: Vec<T> = match response.find(key) {
+                Some(page_json) => try!(Decodable::decode(&mut json::Decoder::new(page_json.clone()))),
+                None => return Err(From::from(json::DecoderError::MissingFieldError(key.to_string()))),
+            };
+
+            let received = page.len();
+            items.extend(page.into_iter());
+
+            let finished = received == 0
+                || total_count.map(|total| items.len() as u64 >= total).unwrap_or(true)
+                || max_items.map(|max| items.len() as u32 >= max).unwrap_or(false);
+
+            if finished {
+                return Ok(items);
+            }
+        }
+    }
+
+    fn get_json(&self, url: Url) -> Result<Json, Error> {
+        self.request_json(Method::Get, url, None)
+    }
+
+    fn request_json(&self, method: Method, url: Url, body: Option<String>) -> Result<Json, Error> {
+        let mut response = try!(self.send_request(Request {
+            method: method,
+            body: body.map(Body::Json),
+            url: url,
         }));
 
         let mut response_contents = String::new();
         try!(response.read_to_string(&mut response_contents));
 
-        let parsed: Users = try!(json::decode(&response_contents));
-
-        Ok(parsed.users)
+        Ok(try!(Json::from_str(&response_contents)))
     }
 
     fn send_request<'a>(&self, request: Request) -> Result<hyper::client::Response, Error> {
@@ -201,7 +682,11 @@ impl Client {
         let mut client = hyper::Client::new();
 
         debug!("Request {} - {} {}", request_id, request.method, request.url);
-        debug!("Request {} - Body: {:?}", request_id, request.body);
+        match request.body {
+            Some(Body::Json(ref json))    => debug!("Request {} - Body: {}", request_id, json),
+            Some(Body::Binary(ref bytes)) => debug!("Request {} - Body: {} bytes", request_id, bytes.len()),
+            None                          => debug!("Request {} - No body", request_id),
+        }
 
         let url = request.url.clone();
 
@@ -212,16 +697,22 @@ impl Client {
             Method::Delete => client.delete(url),
         };
 
+        let content_type = match request.body {
+            Some(Body::Binary(_)) => "application/octet-stream",
+            _                     => "application/json",
+        };
+
         let request_with_headers = request_builder
             .header(RedmineApiKey(self.config.redmine_key().to_string()))
-            .header(header::ContentType("application/json".parse().unwrap()));
+            .header(header::ContentType(content_type.parse().unwrap()));
 
         let complete_request = match request.body {
-            None        => request_with_headers,
-            Some(ref s) => request_with_headers.body(&s[..]),
+            None                          => request_with_headers,
+            Some(Body::Json(ref s))       => request_with_headers.body(&s[..]),
+            Some(Body::Binary(ref bytes)) => request_with_headers.body(&bytes[..]),
         };
 
-        let response = try!(complete_request.send());
+        let mut response = try!(complete_request.send());
         debug!("Request {} - Received response: {}", request_id, response.status);
 
         match (response.status, response.status.class()) {
@@ -229,6 +720,23 @@ impl Client {
                 Err(Error::Forbidden(request.method, request.url))
             },
 
+            (StatusCode::UnprocessableEntity, _) => {
+                #[derive(RustcDecodable)]
+                struct ValidationErrors {
+                    errors: Vec<String>
+                }
+
+                let mut response_contents = String::new();
+                try!(response.read_to_string(&mut response_contents));
+
+                let parsed: ValidationErrors = try!(json::decode(&response_contents));
+                Err(Error::Validation(parsed.errors))
+            },
+
+            (StatusCode::Conflict, _) => {
+                Err(Error::Conflict(request.method, request.url))
+            },
+
             (_, StatusClass::ServerError) => {
                 Err(Error::Server(request.method, request.url))
             },
@@ -249,6 +757,15 @@ impl Client {
         self.build_url(&format!("issues/{}.json", number))
     }
 
+    fn wiki_page_url(&self, project: &str, title: &str, version: Option<u32>) -> Url {
+        let title = utf8_percent_encode(title, DEFAULT_ENCODE_SET);
+
+        match version {
+            Some(version) => self.build_url(&format!("projects/{}/wiki/{}/{}.json", project, title, version)),
+            None          => self.build_url(&format!("projects/{}/wiki/{}.json", project, title)),
+        }
+    }
+
     fn build_url(&self, path: &str) -> Url {
         let request_url = self.config.redmine_url();
 
@@ -258,3 +775,37 @@ impl Client {
             .unwrap()
     }
 }
+
+/// Turns every custom field value found on an issue payload into an array, so that single and
+/// multi-value fields decode the same way
+fn normalize_custom_values(json: &mut Json) {
+    match *json {
+        Json::Object(ref mut object) => {
+            for (key, value) in object.iter_mut() {
+                if *key == "custom_fields" {
+                    for field in value.as_array_mut().into_iter().flat_map(|fields| fields.iter_mut()) {
+                        if let Some(field) = field.as_object_mut() {
+                            let normalized = match field.remove("value") {
+                                Some(Json::Array(values)) => Json::Array(values),
+                                Some(Json::Null) | None   => Json::Array(Vec::new()),
+                                Some(single)              => Json::Array(vec![single]),
+                            };
+
+                            field.insert("value".to_string(), normalized);
+                        }
+                    }
+                } else {
+                    normalize_custom_values(value);
+                }
+            }
+        },
+
+        Json::Array(ref mut array) => {
+            for item in array.iter_mut() {
+                normalize_custom_values(item);
+            }
+        },
+
+        _ => {},
+    }
+}
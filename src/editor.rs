@@ -0,0 +1,84 @@
+/* rdm - A command-line redmine client
+ * Copyright (C) 2015 Renato Zannon
+ *
+ * This program is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation; either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program; if not, see <http://www.gnu.org/licenses/>. */
+
+use std::io::prelude::*;
+use std::io;
+use std::env;
+use std::fs::{self, File};
+use std::process::Command;
+
+use libc;
+use uuid::Uuid;
+
+/// Whether there's someone at a terminal to use the editor. Scripts and CI jobs, whose stdin
+/// isn't a terminal, would otherwise hang waiting on an editor nobody sees.
+pub fn is_interactive() -> bool {
+    unsafe { libc::isatty(0) != 0 }
+}
+
+/// Opens `$VISUAL` (or `$EDITOR`, or `vi`) on a temporary file holding `initial`, returning what
+/// the file contains once the editor exits.
+pub fn edit(initial: &str, extension: &str) -> io::Result<String> {
+    let editor = env::var("VISUAL").or(env::var("EDITOR")).unwrap_or("vi".to_string());
+
+    let mut path = env::temp_dir();
+    path.push(format!("rdm-{}.{}", Uuid::new_v4(), extension));
+
+    {
+        let mut file = try!(File::create(&path));
+        try!(file.write_all(initial.as_bytes()));
+    }
+
+    // The editor setting may carry arguments (e.g. "code --wait"), so let the shell split it
+    let status = try!(Command::new("sh")
+        .arg("-c")
+        .arg(format!("{} \"$1\"", editor))
+        .arg("sh")
+        .arg(&path)
+        .status());
+
+    let mut contents = String::new();
+    let read_result = File::open(&path).and_then(|mut file| file.read_to_string(&mut contents));
+    let _ = fs::remove_file(&path);
+
+    if !status.success() {
+        return Err(io::Error::new(io::ErrorKind::Other,
+                                  format!("Editor '{}' exited with {}", editor, status)));
+    }
+
+    try!(read_result);
+    Ok(contents)
+}
+
+/// Drops the lines starting with `#` and the surrounding blank lines, git-commit style
+pub fn strip_comments(text: &str) -> String {
+    let lines: Vec<&str> = text.lines().filter(|line| !line.starts_with("#")).collect();
+    lines.connect("\n").trim().to_string()
+}
+
+/// Prefixes every line of `text` with `# `, so that it can be shown on an edited file without
+/// becoming part of the result
+pub fn comment_out(text: &str) -> String {
+    let mut commented = String::new();
+
+    for line in text.lines() {
+        commented.push_str(if line.is_empty() { "#" } else { "# " });
+        commented.push_str(line);
+        commented.push('\n');
+    }
+
+    commented
+}
@@ -0,0 +1,325 @@
+/* rdm - A command-line redmine client
+ * Copyright (C) 2015 Renato Zannon
+ *
+ * This program is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation; either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program; if not, see <http://www.gnu.org/licenses/>. */
+
+use std::io::prelude::*;
+use std::io::{self, BufReader};
+use std::fs::File;
+use std::path::PathBuf;
+use std::collections::HashMap;
+
+use rustc_serialize::json;
+use time;
+
+use args;
+use user_config::Config;
+
+/// A time entry read from another tool, not yet sent to Redmine
+#[derive(Debug, Clone)]
+pub struct ImportRow {
+    pub date: String,
+    pub issue: u32,
+    pub hours: f64,
+    pub activity: Option<String>,
+    pub comment: Option<String>,
+
+    /// Identifies the row across imports of the same file, so that it is never imported twice
+    pub fingerprint: String,
+}
+
+/// Parses a CSV file with a header row. The `date` (YYYY-MM-DD), `issue` and `hours` columns are
+/// required; `activity` and `comment` are optional.
+pub fn parse_csv(text: &str) -> Result<Vec<ImportRow>, String> {
+    let mut records = try!(split_csv(text)).into_iter();
+
+    let header: Vec<String> = match records.next() {
+        Some((_, fields)) => fields.into_iter().map(|h| h.trim().to_lowercase()).collect(),
+        None              => return Ok(Vec::new()),
+    };
+
+    let column = |name: &str| header.iter().position(|h| h == name);
+
+    let date_column  = try!(column("date").ok_or("CSV is missing the 'date' column".to_string()));
+    let issue_column = try!(column("issue").ok_or("CSV is missing the 'issue' column".to_string()));
+    let hours_column = try!(column("hours").ok_or("CSV is missing the 'hours' column".to_string()));
+    let activity_column = column("activity");
+    let comment_column  = column("comment");
+
+    let mut rows: Vec<ImportRow> = Vec::new();
+    let mut occurrences: HashMap<String, usize> = HashMap::new();
+
+    for (line, fields) in records {
+        let field = |index: usize| fields.get(index).map(|f| f.trim().to_string()).unwrap_or(String::new());
+        let optional = |index: Option<usize>| index.map(|i| field(i)).and_then(|f| if f.is_empty() { None } else { Some(f) });
+
+        let date = field(date_column);
+        let issue = field(issue_column);
+        let hours = field(hours_column);
+
+        if !args::is_date(&date) {
+            return Err(format!("Invalid date '{}' on line {}: expected YYYY-MM-DD", date, line));
+        }
+
+        let mut row = ImportRow {
+            date: date,
+            issue: try!(issue.trim_left_matches('#').parse()
+                             .map_err(|_| format!("Invalid issue '{}' on line {}", issue, line))),
+            hours: try!(parse_hours(&hours).ok_or(format!("Invalid hours '{}' on line {}", hours, line))),
+            activity: optional(activity_column),
+            comment: optional(comment_column),
+            fingerprint: String::new(),
+        };
+
+        // Identical rows are told apart by how many of them came before
+        let base = format!("csv|{}|{}|{:.2}|{}|{}", row.date, row.issue, row.hours,
+                           row.activity.clone().unwrap_or(String::new()),
+                           row.comment.clone().unwrap_or(String::new()));
+        let occurrence = *occurrences.get(&base).unwrap_or(&0);
+        row.fingerprint = format!("{}|{}", base, occurrence);
+        occurrences.insert(base, occurrence + 1);
+
+        rows.push(row);
+    }
+
+    Ok(rows)
+}
+
+/// Parses the output of `timew export`. The issue comes from a `#<number>` tag and the activity
+/// from an `activity:<name>` tag; intervals without an issue or still running are left out.
+pub fn parse_timew(text: &str) -> Result<Vec<ImportRow>, String> {
+    #[derive(RustcDecodable)]
+    struct Interval {
+        start: String,
+        end: Option<String>,
+        tags: Option<Vec<String>>,
+        annotation: Option<String>,
+    }
+
+    let intervals: Vec<Interval> = try!(json::decode(text).map_err(|err| format!("Invalid timewarrior export: {}", err)));
+    let mut rows = Vec::new();
+
+    for interval in intervals.into_iter() {
+        let end = match interval.end {
+            Some(end) => end,
+            None      => continue,
+        };
+
+        let tags = interval.tags.unwrap_or(Vec::new());
+
+        let issue = tags.iter()
+            .filter(|tag| tag.starts_with("#"))
+            .filter_map(|tag| tag[1..].parse::<u32>().ok())
+            .next();
+
+        let issue = match issue {
+            Some(issue) => issue,
+            None        => continue,
+        };
+
+        let activity = tags.iter()
+            .find(|tag| tag.starts_with("activity:"))
+            .map(|tag| tag["activity:".len()..].to_string());
+
+        let start = try!(parse_timew_time(&interval.start));
+        let finish = try!(parse_timew_time(&end));
+
+        rows.push(ImportRow {
+            date: time::strftime("%Y-%m-%d", &time::at(start)).unwrap(),
+            issue: issue,
+            hours: (finish - start).num_seconds() as f64 / 3600.0,
+            activity: activity,
+            comment: interval.annotation,
+            fingerprint: format!("timew|{}|{}", interval.start, end),
+        });
+    }
+
+    Ok(rows)
+}
+
+fn parse_timew_time(timestamp: &str) -> Result<time::Timespec, String> {
+    time::strptime(timestamp, "%Y%m%dT%H%M%SZ")
+        .map(|tm| tm.to_timespec())
+        .map_err(|_| format!("Invalid timewarrior timestamp '{}'", timestamp))
+}
+
+/// Accepts decimal hours (1.5) and hours:minutes (1:30)
+fn parse_hours(hours: &str) -> Option<f64> {
+    let parts: Vec<&str> = hours.split(':').collect();
+
+    match parts.len() {
+        1 => parts[0].parse().ok(),
+        2 => match (parts[0].parse::<f64>(), parts[1].parse::<f64>()) {
+            (Ok(h), Ok(m)) => Some(h + m / 60.0),
+            _              => None,
+        },
+        _ => None,
+    }
+}
+
+/// Splits CSV text into records, each with the line it starts on. Quoted fields may span lines;
+/// blank lines are skipped.
+fn split_csv(text: &str) -> Result<Vec<(usize, Vec<String>)>, String> {
+    let mut records = Vec::new();
+    let mut fields = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut line = 1;
+    let mut record_line = 1;
+    let mut chars = text.chars().peekable();
+
+    loop {
+        let chr = chars.next();
+
+        match chr {
+            Some('"') if in_quotes && chars.peek() == Some(&'"') => {
+                chars.next();
+                current.push('"');
+            },
+            Some('"') => in_quotes = !in_quotes,
+            Some(',') if !in_quotes => {
+                fields.push(current);
+                current = String::new();
+            },
+            Some('\r') if !in_quotes && chars.peek() == Some(&'\n') => (),
+            Some('\n') | None if !in_quotes => {
+                fields.push(current);
+                current = String::new();
+
+                let blank = fields.len() == 1 && fields[0].trim().is_empty();
+                if !blank {
+                    records.push((record_line, fields));
+                }
+
+                fields = Vec::new();
+                line += 1;
+                record_line = line;
+            },
+            Some(chr) => {
+                if chr == '\n' {
+                    line += 1;
+                }
+
+                current.push(chr);
+            },
+            None => return Err(format!("Unterminated quote on line {}", record_line)),
+        }
+
+        if chr.is_none() {
+            break;
+        }
+    }
+
+    Ok(records)
+}
+
+/// The rows imported so far, by fingerprint, with the id of the time entry each one became.
+/// Kept on `.rdm-imports.json`, next to the config file.
+pub struct Ledger {
+    path: PathBuf,
+    imported: HashMap<String, u32>,
+}
+
+impl Ledger {
+    pub fn load(config: &Config) -> Result<Ledger, io::Error> {
+        let path = config.path().with_file_name(".rdm-imports.json");
+
+        let imported = if path.exists() {
+            let mut contents = String::new();
+            try!(BufReader::new(try!(File::open(&path))).read_to_string(&mut contents));
+
+            try!(json::decode(&contents).map_err(|err| {
+                io::Error::new(io::ErrorKind::InvalidInput, format!("Invalid import ledger: {}", err))
+            }))
+        } else {
+            HashMap::new()
+        };
+
+        Ok(Ledger { path: path, imported: imported })
+    }
+
+    pub fn imported_as(&self, row: &ImportRow) -> Option<u32> {
+        self.imported.get(&row.fingerprint).map(|id| *id)
+    }
+
+    pub fn record(&mut self, row: &ImportRow, entry_id: u32) -> Result<(), io::Error> {
+        self.imported.insert(row.fingerprint.clone(), entry_id);
+
+        let mut file = try!(File::create(&self.path));
+        write!(&mut file, "{}", json::encode(&self.imported).unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_csv, split_csv};
+
+    #[test]
+    fn quoted_fields_may_hold_commas_quotes_and_newlines() {
+        let records = split_csv("a,\"b, \"\"c\"\"\",d\n\"multi\nline\",e\nf,g").unwrap();
+
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0], (1, vec!["a".to_string(), "b, \"c\"".to_string(), "d".to_string()]));
+        assert_eq!(records[1], (2, vec!["multi\nline".to_string(), "e".to_string()]));
+        assert_eq!(records[2], (4, vec!["f".to_string(), "g".to_string()]));
+    }
+
+    #[test]
+    fn blank_lines_and_crlf_endings_are_ignored() {
+        let records = split_csv("a,b\r\n\r\nc,d\r\n").unwrap();
+
+        assert_eq!(records, vec![
+            (1, vec!["a".to_string(), "b".to_string()]),
+            (3, vec!["c".to_string(), "d".to_string()]),
+        ]);
+    }
+
+    #[test]
+    fn unterminated_quotes_are_rejected() {
+        assert!(split_csv("a,\"b\nc,d").is_err());
+    }
+
+    #[test]
+    fn rows_are_read_by_column_name() {
+        let csv = "Hours,Issue,Date,Comment\n1:30,#12,2015-04-01,\"Fixed it,\nfinally\"\n2,13,2015-04-02,\n";
+        let rows = parse_csv(csv).unwrap();
+
+        assert_eq!(rows.len(), 2);
+
+        assert_eq!(rows[0].date, "2015-04-01");
+        assert_eq!(rows[0].issue, 12);
+        assert_eq!(rows[0].hours, 1.5);
+        assert_eq!(rows[0].activity, None);
+        assert_eq!(rows[0].comment, Some("Fixed it,\nfinally".to_string()));
+
+        assert_eq!(rows[1].issue, 13);
+        assert_eq!(rows[1].comment, None);
+    }
+
+    #[test]
+    fn identical_rows_get_distinct_fingerprints() {
+        let rows = parse_csv("date,issue,hours\n2015-04-01,12,1\n2015-04-01,12,1\n").unwrap();
+
+        assert!(rows[0].fingerprint != rows[1].fingerprint);
+    }
+
+    #[test]
+    fn invalid_rows_are_rejected() {
+        assert!(parse_csv("issue,hours\n12,1\n").is_err());
+        assert!(parse_csv("date,issue,hours\n2015-02-30,12,1\n").is_err());
+        assert!(parse_csv("date,issue,hours\n04/01/2015,12,1\n").is_err());
+        assert!(parse_csv("date,issue,hours\n2015-04-01,abc,1\n").is_err());
+        assert!(parse_csv("date,issue,hours\n2015-04-01,12,1h\n").is_err());
+    }
+}
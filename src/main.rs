@@ -21,6 +21,7 @@ extern crate docopt;
 extern crate url;
 extern crate time;
 extern crate uuid;
+extern crate libc;
 
 #[macro_use]
 extern crate log;
@@ -29,15 +30,35 @@ extern crate env_logger;
 #[macro_use(header, deref)]
 extern crate hyper;
 
-use std::{fmt, env};
+use std::env;
+use std::error::Error;
+use std::io::{self, Read, Write};
+use std::fs::File;
+use std::path::Path;
 
 mod models;
 mod args;
 mod client;
 mod user_config;
 mod cache;
+mod resolve;
+mod render;
+mod editor;
+mod timer;
+mod timesheet;
+mod import;
+mod changelog;
+mod wiki_sync;
+mod terminal;
 
-use args::{Args, parse};
+use args::{Args, Status, Filter, IssueFilters, IssueListOptions, IssueAttributes, TimeEntryAttributes,
+           VersionAttributes, NotesSource, ImportFormat, SyncDirection, parse};
+use client::Fields;
+use rustc_serialize::json::{self, Json, ToJson};
+use models::{Issue, TimeEntry, WikiPage, WikiPageSummary};
+use timer::Timer;
+use timesheet::{Timesheet, Week, Change};
+use user_config::Config;
 
 macro_rules! get_or_exit(
     ($result:expr, $err_p:pat => $err_e:expr) => {
@@ -65,50 +86,1250 @@ fn main() {
     let mut cache = get_or_exit!(cache::Cache::new(&mut client));
 
     match args {
-        Args::CloseIssue { number, close_status } => {
+        Args::CloseIssue { number, close_status, notes, private_notes } => {
             let status_name = close_status
                 .or_else(move || config.default_close_status().map(|s| s.to_string()))
                 .expect("Unable to determine which status name to use");
 
-            let status_id = get_or_exit!(find_status_id(&mut cache, &client, &status_name));
+            let status_id = get_or_exit!(resolve::status_id(&mut cache, &client, &status_name));
+            let notes = get_or_exit!(issue_notes(&mut cache, &client, number, notes));
 
-            get_or_exit!(client.update_issue(number, status_id));
+            let mut fields = Fields::issue();
+            fields.set("status_id", &status_id);
+            get_or_exit!(set_notes(&mut fields, notes, private_notes));
+
+            get_or_exit!(client.update_issue(number, &fields));
+        },
+
+        Args::ListProjects { limit } => {
+            let projects = get_or_exit!(cache.projects(&client));
+
+            // The whole list is needed to lay out the tree, so the limit applies to its lines
+            for line in render::project_tree(&projects).lines().take(max_items(limit)) {
+                println!("{}", line);
+            }
+        },
+
+        Args::ShowProject { project } => {
+            let identifier = get_or_exit!(resolve::project(&mut cache, &client, &project)).identifier;
+
+            let project = get_or_exit!(client.project(&identifier));
+            let memberships = get_or_exit!(client.memberships(&identifier));
+
+            print!("{}", render::project_details(&project, &memberships));
+        },
+
+        Args::CreateProject { name, identifier, description, parent, is_public } => {
+            let mut fields = Fields::project();
+            fields.set("name", &name).set("identifier", &identifier);
+
+            if let Some(ref description) = description {
+                fields.set("description", description);
+            }
+
+            if let Some(ref parent) = parent {
+                fields.set("parent_id", &get_or_exit!(resolve::project(&mut cache, &client, parent)).id);
+            }
+
+            if let Some(is_public) = is_public {
+                fields.set("is_public", &is_public);
+            }
+
+            let project = get_or_exit!(client.create_project(&fields));
+            cache.forget_projects();
+
+            println!("Created project {} ({})", project.name, project.identifier);
+        },
+
+        Args::ListVersions { project, limit } => {
+            let identifier = get_or_exit!(resolve::project(&mut cache, &client, &project)).identifier;
+            let versions = get_or_exit!(client.versions(&identifier));
+
+            for version in versions.iter().take(max_items(limit)) {
+                let (open, closed) = get_or_exit!(version_issue_counts(&client, version.id));
+                println!("{}", render::version_line(version, open, closed));
+            }
+        },
+
+        Args::ShowVersion { id } => {
+            let version = get_or_exit!(client.version(id));
+            let (open, closed) = get_or_exit!(version_issue_counts(&client, id));
+
+            print!("{}", render::version_details(&version, open, closed));
+        },
+
+        Args::CreateVersion { project, attributes } => {
+            let identifier = get_or_exit!(resolve::project(&mut cache, &client, &project)).identifier;
+
+            let version = get_or_exit!(client.create_version(&identifier, &version_fields(&attributes)));
+            cache.forget_versions(&client, &identifier);
+
+            println!("Created version {} ({})", version.name, version.id);
+        },
+
+        Args::UpdateVersion { id, attributes } => {
+            let fields = version_fields(&attributes);
+
+            if fields.is_empty() {
+                println!("Nothing to update");
+                env::set_exit_status(1);
+                return;
+            }
+
+            let version = get_or_exit!(client.version(id));
+            get_or_exit!(client.update_version(id, &fields));
+
+            cache.forget_versions(&client, &version.project.id.to_string());
+        },
+
+        Args::RolloverVersion { id, to, close, confirmed } => {
+            let version = get_or_exit!(client.version(id));
+            let project = version.project.id.to_string();
+
+            let target_id = get_or_exit!(resolve::version_id(&mut cache, &client, &project, &to));
+            if target_id == id {
+                println!("Can't roll version {} over to itself", version.name);
+                env::set_exit_status(1);
+                return;
+            }
+
+            let target = get_or_exit!(client.version(target_id));
+            let closed_statuses = get_or_exit!(cache.closed_status_ids(&client));
+
+            let params = vec![
+                ("fixed_version_id".to_string(), id.to_string()),
+                ("status_id".to_string(), "*".to_string()),
+            ];
+
+            let unfinished: Vec<Issue> = get_or_exit!(client.issues(&params, None)).into_iter()
+                .filter(|issue| !closed_statuses.contains(&issue.status.id))
+                .collect();
+
+            if unfinished.is_empty() {
+                println!("No open issues on {}", version.name);
+            } else {
+                println!("Open issues on {}:\n", version.name);
+
+                for issue in unfinished.iter() {
+                    println!("{}", format_issue_line(issue));
+                }
+
+                println!("");
+
+                let mut fields = Fields::issue();
+                fields.set("fixed_version_id", &target.id);
+
+                for issue in unfinished.iter() {
+                    let question = format!("Move #{} {} to {}?", issue.id, issue.subject, target.name);
+
+                    if !confirmed && !get_or_exit!(confirm(&question)) {
+                        println!("Left #{} on {}", issue.id, version.name);
+                        continue;
+                    }
+
+                    get_or_exit!(client.update_issue(issue.id, &fields));
+                    println!("Moved #{} to {}", issue.id, target.name);
+                }
+            }
+
+            if close {
+                let mut fields = Fields::version();
+                fields.set("status", "closed");
+
+                get_or_exit!(client.update_version(id, &fields));
+                cache.forget_versions(&client, &project);
+
+                println!("Closed version {}", version.name);
+            }
+        },
+
+        Args::ShowWikiIndex { project } => {
+            let identifier = get_or_exit!(resolve::project(&mut cache, &client, &project)).identifier;
+            let pages = get_or_exit!(client.wiki_pages(&identifier));
+
+            print!("{}", render::wiki_index(&pages));
+        },
+
+        Args::ShowWikiPage { project, page, version } => {
+            let identifier = get_or_exit!(resolve::project(&mut cache, &client, &project)).identifier;
+            let page = get_or_exit!(client.wiki_page(&identifier, &page, version));
+
+            print!("{}", render::wiki_page(&page));
+        },
+
+        Args::EditWikiPage { project, page, comment } => {
+            let identifier = get_or_exit!(resolve::project(&mut cache, &client, &project)).identifier;
+            let current = get_or_exit!(client.wiki_page(&identifier, &page, None));
+
+            let text = get_or_exit!(editor::edit(&current.text, "txt"));
+
+            if text.trim_right() == current.text.trim_right() {
+                println!("No changes to {}", current.title);
+                return;
+            }
+
+            // Sending the edited version makes Redmine refuse the update if someone else saved meanwhile
+            let mut fields = Fields::wiki_page();
+            fields.set("text", &text).set("version", &current.version);
+
+            if let Some(ref comment) = comment {
+                fields.set("comments", comment);
+            }
+
+            get_or_exit!(client.update_wiki_page(&identifier, &current.title, &fields));
+            println!("Updated {} to version {}", current.title, current.version + 1);
+        },
+
+        Args::WikiHistory { project, page, limit } => {
+            if limit == Some(0) {
+                return;
+            }
+
+            let identifier = get_or_exit!(resolve::project(&mut cache, &client, &project)).identifier;
+            let latest = get_or_exit!(client.wiki_page(&identifier, &page, None));
+
+            // The API has no history listing, so each version is fetched on its own. The latest
+            // counts towards the limit, so at most `limit - 1` older ones are fetched.
+            let oldest = limit.map(|limit| latest.version.saturating_sub(limit) + 1).unwrap_or(1);
+
+            println!("{}", render::wiki_version_line(&latest));
+
+            for version in (oldest..latest.version).rev() {
+                let page = get_or_exit!(client.wiki_page(&identifier, &latest.title, Some(version)));
+                println!("{}", render::wiki_version_line(&page));
+            }
+        },
+
+        Args::SyncWiki { project, dir, direction } => {
+            let identifier = get_or_exit!(resolve::project(&mut cache, &client, &project)).identifier;
+            let dir = Path::new(&dir);
+
+            let mut state = get_or_exit!(wiki_sync::SyncState::load(dir, &identifier));
+            let local = get_or_exit!(wiki_sync::local_pages(dir));
+            let remote = get_or_exit!(client.wiki_pages(&identifier));
+
+            let mut titles: Vec<&str> = local.iter().map(|page| &page.title[..])
+                .chain(remote.iter().map(|page| &page.title[..]))
+                .collect();
+            titles.sort();
+            titles.dedup();
+
+            let mut conflicts = 0;
+
+            for title in titles {
+                let local_page = local.iter().find(|page| page.title == title);
+                let remote_page = remote.iter().find(|page| page.title == title);
+
+                let action = wiki_sync::plan(local_page, remote_page, state.get(title));
+                let outcome = get_or_exit!(sync_wiki_page(&client, &mut state, &identifier, dir, title,
+                                                          local_page, remote_page, action, direction));
+
+                if outcome == wiki_sync::Outcome::Conflict {
+                    conflicts += 1;
+                }
+
+                if action != wiki_sync::Action::UpToDate {
+                    println!("{:<10} {}", outcome, title);
+                }
+            }
+
+            if conflicts > 0 {
+                println!("\n{} pages changed on both sides since the last sync; merge them by hand", conflicts);
+                env::set_exit_status(1);
+            }
+        },
+
+        Args::Changelog { version, project, format } => {
+            let project_query = project.or_else(|| config.default_project().map(|p| p.to_string()));
+
+            // Ids are taken as they are, so that a version of any project can be given by id
+            let version_id = match (version.parse::<u32>(), project_query) {
+                (Ok(id), _) => id,
+                (Err(_), Some(ref query)) => {
+                    let identifier = get_or_exit!(resolve::project(&mut cache, &client, query)).identifier;
+                    get_or_exit!(resolve::version_id(&mut cache, &client, &identifier, &version))
+                },
+                (Err(_), None) => {
+                    println!("Versions can only be found by name with --project or the 'default_project' setting");
+                    env::set_exit_status(1);
+                    return;
+                },
+            };
+
+            let version = get_or_exit!(client.version(version_id));
+
+            let params = vec![
+                ("fixed_version_id".to_string(), version_id.to_string()),
+                ("status_id".to_string(), "closed".to_string()),
+            ];
+            let issues = get_or_exit!(client.issues(&params, None));
+
+            print!("{}", changelog::render(&version, &issues, &config, format));
+        },
+
+        Args::CreateIssue(attributes) => {
+            let project_query = attributes.project.clone()
+                .or_else(|| config.default_project().map(|p| p.to_string()));
+
+            let project = match project_query {
+                Some(ref query) => get_or_exit!(resolve::project(&mut cache, &client, query)).identifier,
+                None => {
+                    println!("--project or the 'default_project' setting is required to create an issue");
+                    env::set_exit_status(1);
+                    return;
+                },
+            };
+
+            let fields = get_or_exit!(issue_fields(&mut cache, &client, &attributes, Some(&project[..])));
+            let number = get_or_exit!(client.create_issue(&project, &fields));
+
+            println!("Created issue #{}", number);
+        },
+
+        Args::ShowIssue { number } => {
+            let issue = get_or_exit!(client.issue(number));
+            let names = render::Names::load(&mut cache, &client);
+
+            print!("{}", render::issue_details(&issue, &names));
+        },
+
+        Args::ListRelations { number } => {
+            let relations = get_or_exit!(client.relations(number));
+
+            for relation in relations.iter() {
+                println!("{:<6} {}", relation.id, render::relation_summary(relation, number));
+            }
+        },
+
+        Args::CreateRelation { number, relation_type, other, delay } => {
+            let relation = get_or_exit!(client.create_relation(number, other, &relation_type, delay));
+            println!("Created relation {}: #{} {}", relation.id, number,
+                     render::relation_summary(&relation, number));
+        },
+
+        Args::DeleteRelation { id } => {
+            get_or_exit!(client.delete_relation(id));
+        },
+
+        Args::Search { terms, project, options, limit } => {
+            let project = match project {
+                Some(ref query) => Some(get_or_exit!(resolve::project(&mut cache, &client, query)).identifier),
+                None            => None,
+            };
+
+            let mut params = vec![("q".to_string(), terms)];
+
+            for search_type in options.types.iter() {
+                params.push((search_type.to_string(), "1".to_string()));
+            }
+
+            if options.titles_only {
+                params.push(("titles_only".to_string(), "1".to_string()));
+            }
+
+            if options.open_issues {
+                params.push(("open_issues".to_string(), "1".to_string()));
+            }
+
+            let results = get_or_exit!(client.search(project.as_ref().map(|p| &p[..]), &params, limit));
+
+            for result in results.iter() {
+                println!("{}", render::search_result(result));
+            }
+        },
+
+        Args::WatchIssue { number, user } => {
+            let query = user.unwrap_or("me".to_string());
+            let user = get_or_exit!(resolve::user(&mut cache, &client, &query));
+
+            get_or_exit!(client.add_watcher(number, user.id));
+            println!("{} is now watching #{}", user.full_name(), number);
+        },
+
+        Args::UnwatchIssue { number, user } => {
+            let query = user.unwrap_or("me".to_string());
+            let user = get_or_exit!(resolve::user(&mut cache, &client, &query));
+
+            get_or_exit!(client.remove_watcher(number, user.id));
+            println!("{} is no longer watching #{}", user.full_name(), number);
+        },
+
+        Args::ListWatching { limit } => {
+            let params = vec![
+                ("watcher_id".to_string(), "me".to_string()),
+                ("status_id".to_string(), "open".to_string()),
+            ];
+
+            let issues = get_or_exit!(client.issues(&params, limit));
+
+            for issue in issues.iter() {
+                println!("{}", format_issue_line(issue));
+            }
+        },
+
+        Args::AttachFiles { number, paths } => {
+            let mut uploads = Vec::new();
+
+            for path in paths.iter() {
+                let mut contents = Vec::new();
+                get_or_exit!(File::open(path).and_then(|mut file| file.read_to_end(&mut contents)));
+
+                let token = get_or_exit!(client.upload(contents));
+                let filename = Path::new(path).file_name().and_then(|name| name.to_str()).unwrap_or(&path[..]);
+
+                let mut upload = json::Object::new();
+                upload.insert("token".to_string(), token.to_json());
+                upload.insert("filename".to_string(), filename.to_json());
+                uploads.push(Json::Object(upload));
+            }
+
+            let mut fields = Fields::issue();
+            fields.set("uploads", &uploads);
+
+            get_or_exit!(client.update_issue(number, &fields));
+        },
+
+        Args::DownloadAttachment { id, output } => {
+            let attachment = get_or_exit!(client.attachment(id));
+            let contents = get_or_exit!(client.download(&attachment));
+
+            let output = match output {
+                Some(output) => output,
+                None => match local_file_name(&attachment.filename) {
+                    Some(name) => name,
+                    None => {
+                        println!("Refusing to save to '{}'; choose a file with --output", attachment.filename);
+                        env::set_exit_status(1);
+                        return;
+                    },
+                },
+            };
+
+            if output == "-" {
+                get_or_exit!(io::stdout().write_all(&contents));
+            } else {
+                get_or_exit!(File::create(&output).and_then(|mut file| file.write_all(&contents)));
+                println!("Saved {} ({} bytes) to {}", attachment.filename, contents.len(), output);
+            }
+        },
+
+        Args::LogTime { attributes } => {
+            let fields = get_or_exit!(time_entry_fields(&mut cache, &client, &attributes));
+            let entry = get_or_exit!(client.create_time_entry(&fields));
+
+            println!("Logged {}h on #{} ({})", entry.hours, attributes.issue.unwrap(), entry.activity.name);
+        },
+
+        Args::ListTimeEntries { user, issue, from, to, limit } => {
+            let mut params = Vec::new();
+
+            if let Some(ref query) = user {
+                let user = get_or_exit!(resolve::user(&mut cache, &client, query));
+                params.push(("user_id".to_string(), user.id.to_string()));
+            }
+
+            if let Some(issue) = issue {
+                params.push(("issue_id".to_string(), issue.to_string()));
+            }
+
+            if let Some(from) = from {
+                params.push(("from".to_string(), from));
+            }
+
+            if let Some(to) = to {
+                params.push(("to".to_string(), to));
+            }
+
+            let entries = get_or_exit!(client.time_entries(&params, limit));
+
+            for entry in entries.iter() {
+                println!("{}", format_time_entry_line(entry));
+            }
+
+            let total = entries.iter().fold(0.0, |total, entry| total + entry.hours);
+            println!("Total: {:.2}h", total);
+        },
+
+        Args::EditTimeEntry { id, attributes } => {
+            let fields = get_or_exit!(time_entry_fields(&mut cache, &client, &attributes));
+
+            if fields.is_empty() {
+                println!("Nothing to update");
+                env::set_exit_status(1);
+                return;
+            }
+
+            get_or_exit!(client.update_time_entry(id, &fields));
+        },
+
+        Args::DeleteTimeEntry { id } => {
+            get_or_exit!(client.delete_time_entry(id));
+        },
+
+        Args::ImportTime { path, format, confirmed } => {
+            let mut contents = String::new();
+            get_or_exit!(File::open(&path).and_then(|mut file| file.read_to_string(&mut contents)));
+
+            let rows = get_or_exit!(match format {
+                ImportFormat::Csv         => import::parse_csv(&contents),
+                ImportFormat::Timewarrior => import::parse_timew(&contents),
+            });
+
+            let mut ledger = get_or_exit!(import::Ledger::load(&config));
+
+            // Resolve everything up front, so that nothing is created when any row is invalid
+            let mut pending = Vec::new();
+
+            println!("{:<11} {:<7} {:>6} {:<14} {:<10} {}", "Date", "Issue", "Hours", "Activity", "Status", "Comment");
+
+            for row in rows.iter() {
+                let status = match ledger.imported_as(row) {
+                    Some(id) => format!("entry {}", id),
+                    None => {
+                        let attributes = TimeEntryAttributes {
+                            issue: Some(row.issue),
+                            hours: Some(format!("{:.2}", row.hours)),
+                            activity: row.activity.clone(),
+                            date: Some(row.date.clone()),
+                            comment: row.comment.clone(),
+                        };
+
+                        pending.push((row, get_or_exit!(time_entry_fields(&mut cache, &client, &attributes))));
+                        "new".to_string()
+                    },
+                };
+
+                println!("{:<11} {:<7} {:>6.2} {:<14} {:<10} {}", row.date, format!("#{}", row.issue), row.hours,
+                         row.activity.as_ref().map(|a| &a[..]).unwrap_or("(default)"), status,
+                         row.comment.as_ref().map(|c| &c[..]).unwrap_or(""));
+            }
+
+            if pending.is_empty() {
+                println!("\nNothing to import");
+                return;
+            }
+
+            let numbers: Vec<u32> = pending.iter().map(|&(row, _)| row.issue).collect();
+            let missing = get_or_exit!(missing_issues(&client, &numbers));
+
+            if !missing.is_empty() {
+                let missing: Vec<String> = missing.iter().map(|number| format!("#{}", number)).collect();
+                println!("\nThese issues don't exist, or aren't visible to you: {}", missing.connect(", "));
+                println!("Nothing was imported");
+                env::set_exit_status(1);
+                return;
+            }
+
+            if !confirmed && !get_or_exit!(confirm(&format!("\nCreate {} time entries?", pending.len()))) {
+                println!("Nothing was imported");
+                return;
+            }
+
+            for (row, fields) in pending.into_iter() {
+                let entry = get_or_exit!(client.create_time_entry(&fields));
+                get_or_exit!(ledger.record(row, entry.id));
+
+                println!("Created entry {}: {:.2}h on #{} on {}", entry.id, entry.hours, row.issue, entry.spent_on);
+            }
+        },
+
+        Args::Timesheet { week, user, edit } => {
+            let week = match week {
+                Some(ref week) => get_or_exit!(Week::parse(week)),
+                None           => Week::current(),
+            };
+
+            let user_query = user.as_ref().map(|u| &u[..]).unwrap_or("me");
+            let owner = get_or_exit!(resolve::user(&mut cache, &client, user_query));
+
+            let params = vec![
+                ("user_id".to_string(), owner.id.to_string()),
+                ("from".to_string(), week.first_day()),
+                ("to".to_string(), week.last_day()),
+            ];
+
+            let entries = get_or_exit!(client.time_entries(&params, None));
+            let sheet = Timesheet::new(week, entries);
+
+            if !edit {
+                print!("{}", sheet.render());
+                return;
+            }
+
+            let edited = get_or_exit!(editor::edit(&sheet.editable_text(), "txt"));
+            let changes = get_or_exit!(sheet.changes(&edited));
+
+            if changes.is_empty() {
+                println!("No changes");
+                return;
+            }
+
+            // Only set the owner of new entries when logging for somebody else
+            let owner_id = if user.is_some() { Some(owner.id) } else { None };
+
+            for change in changes.into_iter() {
+                match apply_timesheet_change(&mut cache, &client, change, owner_id) {
+                    Ok(description) => println!("{}", description),
+                    Err(err) => {
+                        println!("{}", err);
+                        env::set_exit_status(1);
+                    }
+                }
+            }
+        },
+
+        Args::StartTimer { number, activity, comment } => {
+            if let Some(running) = get_or_exit!(timer::current(&config)) {
+                if running.issue == number {
+                    println!("A timer is already running on #{}, since {}", number, running.start_time());
+                    return;
+                }
+
+                get_or_exit!(log_timer(&mut cache, &client, &config, running, None, None));
+            }
+
+            get_or_exit!(timer::save(&config, &Timer::start(number, activity, comment)));
+            println!("Started timer on #{}", number);
+        },
+
+        Args::StopTimer { activity, comment } => {
+            match get_or_exit!(timer::current(&config)) {
+                Some(running) => {
+                    get_or_exit!(log_timer(&mut cache, &client, &config, running, activity, comment));
+                },
+
+                None => {
+                    println!("No timer is running");
+                    env::set_exit_status(1);
+                },
+            }
+        },
+
+        Args::TimerStatus => {
+            match get_or_exit!(timer::current(&config)) {
+                Some(running) => {
+                    println!("Timer running on #{} for {} (since {})", running.issue,
+                             timer::format_duration(running.elapsed_seconds()), running.start_time());
+                },
+
+                None => println!("No timer is running"),
+            }
+        },
+
+        Args::DiscardTimer => {
+            match get_or_exit!(timer::current(&config)) {
+                Some(running) => {
+                    get_or_exit!(timer::clear(&config));
+                    println!("Discarded timer on #{} ({} elapsed)", running.issue,
+                             timer::format_duration(running.elapsed_seconds()));
+                },
+
+                None => println!("No timer is running"),
+            }
+        },
+
+        Args::UpdateIssue { number, attributes, notes, private_notes } => {
+            let notes = get_or_exit!(issue_notes(&mut cache, &client, number, notes));
+
+            if attributes.is_empty() && notes.is_none() {
+                println!("Nothing to update");
+                env::set_exit_status(1);
+                return;
+            }
+
+            let needs_project = attributes.target_version.is_some() || attributes.category.is_some();
+
+            let project = if needs_project {
+                Some(get_or_exit!(client.issue(number)).project.id.to_string())
+            } else {
+                None
+            };
+
+            let mut fields = get_or_exit!(issue_fields(&mut cache, &client, &attributes,
+                                                       project.as_ref().map(|p| &p[..])));
+            get_or_exit!(set_notes(&mut fields, notes, private_notes));
+
+            get_or_exit!(client.update_issue(number, &fields));
+        },
+
+        Args::ListIssues { status, filters, limit, options } => {
+            let mut params = get_or_exit!(issue_filter_params(&mut cache, &client, &status, &filters));
+
+            if let Some(ref sort) = options.sort {
+                params.push(("sort".to_string(), sort.clone()));
+            }
+
+            let issues = get_or_exit!(client.issues(&params, limit));
+            print_issues(&issues, &options);
         },
 
-        Args::UpdateIssue { number, new_status } => {
-            let status_id = get_or_exit!(find_status_id(&mut cache, &client, &new_status));
-            get_or_exit!(client.update_issue(number, status_id));
+        Args::RunQuery { query, limit, options } => {
+            let query = get_or_exit!(resolve::query(&mut cache, &client, &query));
+
+            // Project queries only apply to their project; Redmine needs to be told which one it is
+            let mut params = vec![("query_id".to_string(), query.id.to_string())];
+
+            if let Some(project_id) = query.project_id {
+                params.push(("project_id".to_string(), project_id.to_string()));
+            }
+
+            if let Some(ref sort) = options.sort {
+                params.push(("sort".to_string(), sort.clone()));
+            }
+
+            let issues = get_or_exit!(client.issues(&params, limit));
+            print_issues(&issues, &options);
         },
 
-        _ => unimplemented!(),
+        Args::ListQueries { limit } => {
+            let queries = get_or_exit!(cache.queries(&client));
+            let projects = cache.projects(&client).unwrap_or(Vec::new());
+
+            for query in queries.iter().take(max_items(limit)) {
+                println!("{}", render::query_line(query, &projects));
+            }
+        },
     }
 }
 
-struct NoMatchingStatus<'a>(&'a str);
+/// Turns the filters of `rdm issues` into query parameters, resolving every name through the cache.
+/// Categories and versions are looked up within the filtered project.
+fn issue_filter_params(cache: &mut cache::Cache, client: &client::Client, status: &Status,
+                       filters: &IssueFilters) -> Result<Vec<(String, String)>, resolve::Error>
+{
+    let mut params = Vec::new();
+
+    let status_param = match *status {
+        Status::AllOpen   => "open".to_string(),
+        Status::AllClosed => "closed".to_string(),
+        Status::Specific(ref name) => try!(resolve::status_id(cache, client, name)).to_string(),
+    };
+    params.push(("status_id".to_string(), status_param));
+
+    let project = match filters.project {
+        Some(ref query) => Some(try!(resolve::project(cache, client, query))),
+        None            => None,
+    };
+
+    if let Some(ref project) = project {
+        params.push(("project_id".to_string(), project.id.to_string()));
 
-impl<'a> fmt::Display for NoMatchingStatus<'a> {
-    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-        write!(f, "No issue status matched '{}'", self.0)
+        match filters.subprojects {
+            Some(true)  => params.push(("subproject_id".to_string(), "*".to_string())),
+            Some(false) => params.push(("subproject_id".to_string(), "!*".to_string())),
+            None        => {},
+        }
+    }
+
+    let project = project.as_ref().map(|project| &project.identifier[..]);
+
+    if let Some(ref filter) = filters.assigned_to {
+        let value = try!(filter_value(filter, |name| Ok(try!(resolve::user(cache, client, name)).id.to_string())));
+        params.push(("assigned_to_id".to_string(), value));
+    }
+
+    if let Some(ref filter) = filters.author {
+        let value = try!(filter_value(filter, |name| Ok(try!(resolve::user(cache, client, name)).id.to_string())));
+        params.push(("author_id".to_string(), value));
+    }
+
+    if let Some(ref filter) = filters.tracker {
+        let value = try!(filter_value(filter, |name| Ok(try!(resolve::tracker_id(cache, client, name)).to_string())));
+        params.push(("tracker_id".to_string(), value));
+    }
+
+    if let Some(ref filter) = filters.priority {
+        let value = try!(filter_value(filter, |name| Ok(try!(resolve::priority_id(cache, client, name)).to_string())));
+        params.push(("priority_id".to_string(), value));
     }
-}
 
-fn find_status_id<'a>(cache: &mut cache::Cache, client: &client::Client, status_name: &'a str) -> Result<u32, NoMatchingStatus<'a>> {
-    let statuses = cache.issue_statuses(client).unwrap();
+    if let Some(ref filter) = filters.category {
+        let value = try!(filter_value(filter, |name| {
+            let project = try!(project.ok_or(resolve::Error::NoProject { kind: "category" }));
+            Ok(try!(resolve::category_id(cache, client, project, name)).to_string())
+        }));
+        params.push(("category_id".to_string(), value));
+    }
+
+    if let Some(ref filter) = filters.target_version {
+        let value = try!(filter_value(filter, |name| {
+            let project = try!(project.ok_or(resolve::Error::NoProject { kind: "target version" }));
+            Ok(try!(resolve::version_id(cache, client, project, name)).to_string())
+        }));
+        params.push(("fixed_version_id".to_string(), value));
+    }
 
-    let status = statuses.into_iter().filter_map(|(id, name)| {
-        let matches = status_name.chars().zip(name.chars()).all(|(query_chr, name_chr)| {
-            query_chr.to_lowercase().zip(name_chr.to_lowercase()).all(|(a, b)| a == b)
-        });
+    let unresolved = [
+        ("parent_id", &filters.parent),
+        ("subject", &filters.subject),
+        ("created_on", &filters.created_on),
+        ("updated_on", &filters.updated_on),
+        ("closed_on", &filters.closed_on),
+    ];
 
-        if matches {
-            Some(id)
-        } else {
-            None
+    for &(name, filter) in unresolved.iter() {
+        if let Some(ref filter) = *filter {
+            params.push((name.to_string(), try!(filter_value(filter, |value| Ok(value.to_string())))));
         }
-    }).next();
+    }
+
+    for (field, values) in try!(resolve::custom_values(cache, client, &filters.custom_fields)) {
+        params.push((format!("cf_{}", field.id), values.connect("|")));
+    }
+
+    Ok(params)
+}
+
+/// Writes `filter` the way Redmine expects it on a query parameter. Only plain values go through
+/// `resolve_name`, as operators apply to dates and text rather than names.
+fn filter_value<F>(filter: &Filter, mut resolve_name: F) -> Result<String, resolve::Error>
+    where F: FnMut(&str) -> Result<String, resolve::Error>
+{
+    let value = match *filter {
+        Filter::Is(ref value)             => try!(resolve_name(value)),
+        Filter::Any                       => "*".to_string(),
+        Filter::NoValue                   => "!*".to_string(),
+        Filter::Between(ref from, ref to) => format!("><{}|{}", from, to),
+        Filter::AtLeast(ref value)        => format!(">={}", value),
+        Filter::AtMost(ref value)         => format!("<={}", value),
+        Filter::Contains(ref value)       => format!("~{}", value),
+    };
+
+    Ok(value)
+}
+
+/// Builds the fields for a create or update request, resolving every name through the cache.
+/// Versions and categories are looked up within `project`.
+fn issue_fields(cache: &mut cache::Cache, client: &client::Client, attributes: &IssueAttributes,
+                project: Option<&str>) -> Result<Fields, resolve::Error>
+{
+    let mut fields = Fields::issue();
+
+    if let Some(ref status) = attributes.status {
+        fields.set("status_id", &try!(resolve::status_id(cache, client, status)));
+    }
+
+    if let Some(ref subject) = attributes.subject {
+        fields.set("subject", subject);
+    }
+
+    if let Some(ref description) = attributes.description {
+        fields.set("description", description);
+    }
+
+    if let Some(ref tracker) = attributes.tracker {
+        fields.set("tracker_id", &try!(resolve::tracker_id(cache, client, tracker)));
+    }
+
+    if let Some(ref priority) = attributes.priority {
+        fields.set("priority_id", &try!(resolve::priority_id(cache, client, priority)));
+    }
+
+    if let Some(ref user) = attributes.assigned_to {
+        fields.set("assigned_to_id", &try!(resolve::user(cache, client, user)).id);
+    }
+
+    if let Some(parent) = attributes.parent {
+        fields.set("parent_issue_id", &parent);
+    }
+
+    if let Some(ref date) = attributes.start_date {
+        fields.set("start_date", date);
+    }
+
+    if let Some(ref date) = attributes.due_date {
+        fields.set("due_date", date);
+    }
+
+    if let Some(hours) = attributes.estimated_hours {
+        fields.set("estimated_hours", &hours);
+    }
+
+    if let Some(ratio) = attributes.done_ratio {
+        fields.set("done_ratio", &ratio);
+    }
+
+    if let Some(ref version) = attributes.target_version {
+        let project = try!(project.ok_or(resolve::Error::NoProject { kind: "target version" }));
+        fields.set("fixed_version_id", &try!(resolve::version_id(cache, client, project, version)));
+    }
+
+    if let Some(ref category) = attributes.category {
+        let project = try!(project.ok_or(resolve::Error::NoProject { kind: "category" }));
+        fields.set("category_id", &try!(resolve::category_id(cache, client, project, category)));
+    }
+
+    if let Some(private) = attributes.is_private {
+        fields.set("is_private", &private);
+    }
+
+    if !attributes.custom_fields.is_empty() {
+        let values = try!(resolve::custom_values(cache, client, &attributes.custom_fields));
+
+        let custom_fields: Vec<Json> = values.into_iter().map(|(field, mut values)| {
+            let mut custom_field = json::Object::new();
+            custom_field.insert("id".to_string(), field.id.to_json());
+
+            let value = if field.multiple.unwrap_or(false) || values.len() > 1 {
+                values.to_json()
+            } else {
+                values.remove(0).to_json()
+            };
+
+            custom_field.insert("value".to_string(), value);
+            Json::Object(custom_field)
+        }).collect();
+
+        fields.set("custom_fields", &custom_fields);
+    }
+
+    Ok(fields)
+}
+
+/// Reads the notes for an update of issue `number`. When they're written on $EDITOR, the file
+/// starts with a commented-out summary of the issue; empty notes mean no notes at all.
+fn issue_notes(cache: &mut cache::Cache, client: &client::Client, number: u32, source: NotesSource)
+    -> Result<Option<String>, io::Error>
+{
+    let text = match source {
+        NotesSource::Text(text) => text,
+
+        NotesSource::File(ref path) if path == "-" => {
+            let mut text = String::new();
+            try!(io::stdin().read_to_string(&mut text));
+            text
+        },
+
+        NotesSource::File(path) => {
+            let mut text = String::new();
+            try!(File::open(&path).and_then(|mut file| file.read_to_string(&mut text)));
+            text
+        },
+
+        NotesSource::Editor if !editor::is_interactive() => String::new(),
+
+        NotesSource::Editor => {
+            let mut template = format!("\n# Please enter the notes for issue #{}. Lines starting with '#' will\n\
+                                        # be ignored, and empty notes will add no notes to the issue.\n#\n",
+                                       number);
+
+            if let Ok(issue) = client.issue(number) {
+                let names = render::Names::load(cache, client);
+                template.push_str(&editor::comment_out(&render::issue_details(&issue, &names)));
+            }
+
+            editor::strip_comments(&try!(editor::edit(&template, "txt")))
+        },
+    };
+
+    if text.trim().is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some(text))
+    }
+}
+
+fn set_notes(fields: &mut Fields, notes: Option<String>, private: bool) -> Result<(), &'static str> {
+    match notes {
+        Some(ref notes) => {
+            fields.set("notes", notes);
+
+            if private {
+                fields.set("private_notes", &true);
+            }
+
+            Ok(())
+        },
+
+        None if private => Err("--private-notes was given, but there are no notes to make private"),
+        None            => Ok(()),
+    }
+}
+
+/// Carries out `action` for one wiki page, as far as `direction` allows. Returns what was done.
+fn sync_wiki_page(client: &client::Client, state: &mut wiki_sync::SyncState, project: &str, dir: &Path,
+                  title: &str, local: Option<&wiki_sync::LocalPage>, remote: Option<&WikiPageSummary>,
+                  action: wiki_sync::Action, direction: SyncDirection) -> Result<wiki_sync::Outcome, Box<Error>>
+{
+    use wiki_sync::{Action, Outcome};
+
+    match action {
+        Action::UpToDate => Ok(Outcome::Unchanged),
+
+        Action::Push if direction == SyncDirection::Pull => Ok(Outcome::Skipped),
+        Action::Pull if direction == SyncDirection::Push => Ok(Outcome::Skipped),
+
+        Action::Push => {
+            // Lets Redmine refuse the upload if the page changed after `plan` looked at it
+            let version = remote.map(|remote| remote.version);
+            push_wiki_page(client, state, project, title, local.unwrap(), version)
+        },
+
+        Action::Pull => {
+            let page = try!(client.wiki_page(project, title, None));
+            pull_wiki_page(state, dir, title, local, page)
+        },
+
+        Action::Conflict => {
+            // Both sides may have made the very same change, e.g. on a first sync
+            let page = try!(client.wiki_page(project, title, None));
+            let text = wiki_sync::normalize(&page.text);
+
+            let local = local.unwrap();
+
+            if text.trim_right() == wiki_sync::normalize(&local.text).trim_right() {
+                try!(state.record(&page, &local.text));
+                return Ok(Outcome::Unchanged);
+            }
+
+            // --push and --pull settle conflicts in favour of their side
+            match direction {
+                SyncDirection::Push => {
+                    let version = page.version;
+                    push_wiki_page(client, state, project, title, local, Some(version))
+                },
+                SyncDirection::Pull => pull_wiki_page(state, dir, title, Some(local), page),
+                SyncDirection::Both => Ok(Outcome::Conflict),
+            }
+        },
+    }
+}
+
+fn push_wiki_page(client: &client::Client, state: &mut wiki_sync::SyncState, project: &str, title: &str,
+                  local: &wiki_sync::LocalPage, version: Option<u32>) -> Result<wiki_sync::Outcome, Box<Error>>
+{
+    let mut fields = Fields::wiki_page();
+    fields.set("text", &local.text);
+
+    if let Some(version) = version {
+        fields.set("version", &version);
+    }
+
+    try!(client.update_wiki_page(project, title, &fields));
+
+    let page = try!(client.wiki_page(project, title, None));
+    try!(state.record(&page, &local.text));
+
+    Ok(wiki_sync::Outcome::Pushed)
+}
+
+fn pull_wiki_page(state: &mut wiki_sync::SyncState, dir: &Path, title: &str, local: Option<&wiki_sync::LocalPage>,
+                  page: WikiPage) -> Result<wiki_sync::Outcome, Box<Error>>
+{
+    let text = wiki_sync::normalize(&page.text);
+
+    let path = local.map(|local| local.path.clone()).unwrap_or(dir.join(format!("{}.md", title)));
+    try!(try!(File::create(&path)).write_all(text.as_bytes()));
+    try!(state.record(&page, &text));
+
+    Ok(wiki_sync::Outcome::Pulled)
+}
+
+/// Which of `numbers` Redmine knows no issue for, checked with a single request
+fn missing_issues(client: &client::Client, numbers: &[u32]) -> Result<Vec<u32>, client::Error> {
+    let mut numbers = numbers.to_vec();
+    numbers.sort();
+    numbers.dedup();
+
+    let ids: Vec<String> = numbers.iter().map(|number| number.to_string()).collect();
+    let params = vec![
+        ("issue_id".to_string(), ids.connect(",")),
+        ("status_id".to_string(), "*".to_string()),
+    ];
+
+    let found = try!(client.issues(&params, None));
+
+    Ok(numbers.into_iter().filter(|number| !found.iter().any(|issue| issue.id == *number)).collect())
+}
 
-    match status {
-        Some(status) => Ok(status),
-        None => Err(NoMatchingStatus(status_name)),
+/// Counts the open and closed issues targeted at a version
+fn version_issue_counts(client: &client::Client, version: u32) -> Result<(u64, u64), client::Error> {
+    let by_status = |status: &str| vec![
+        ("fixed_version_id".to_string(), version.to_string()),
+        ("status_id".to_string(), status.to_string()),
+    ];
+
+    let open   = try!(client.issue_count(&by_status("open")));
+    let closed = try!(client.issue_count(&by_status("closed")));
+
+    Ok((open, closed))
+}
+
+fn version_fields(attributes: &VersionAttributes) -> Fields {
+    let mut fields = Fields::version();
+
+    if let Some(ref name) = attributes.name {
+        fields.set("name", name);
+    }
+
+    if let Some(ref date) = attributes.due_date {
+        fields.set("due_date", date);
     }
+
+    if let Some(ref description) = attributes.description {
+        fields.set("description", description);
+    }
+
+    if let Some(ref status) = attributes.status {
+        fields.set("status", status);
+    }
+
+    fields
+}
+
+/// Stops `running`, logging the elapsed time (rounded as configured) as a time entry on the day
+/// the timer was started. The timer is kept if the entry can't be created.
+fn log_timer(cache: &mut cache::Cache, client: &client::Client, config: &Config, running: Timer,
+             activity: Option<String>, comment: Option<String>) -> Result<(), Box<Error>>
+{
+    let elapsed = running.elapsed_seconds();
+    let minutes = timer::round_minutes(elapsed, config.timer_rounding_minutes(), config.timer_rounding());
+
+    if minutes == 0 {
+        println!("Timer on #{} ran for {}, which rounds to no time at all; nothing was logged",
+                 running.issue, timer::format_duration(elapsed));
+    } else {
+        let attributes = TimeEntryAttributes {
+            issue: Some(running.issue),
+            hours: Some(format!("{:.2}", minutes as f64 / 60.0)),
+            activity: activity.or(running.activity.clone()),
+            date: Some(running.start_date()),
+            comment: comment.or(running.comment.clone()),
+        };
+
+        let fields = try!(time_entry_fields(cache, client, &attributes));
+        let entry = try!(client.create_time_entry(&fields));
+
+        println!("Logged {}h on #{} ({} elapsed)", entry.hours, running.issue, timer::format_duration(elapsed));
+    }
+
+    try!(timer::clear(config));
+    Ok(())
+}
+
+fn apply_timesheet_change(cache: &mut cache::Cache, client: &client::Client, change: Change,
+                          owner: Option<u32>) -> Result<String, Box<Error>>
+{
+    match change {
+        Change::Create { issue, activity, date, hours } => {
+            let attributes = TimeEntryAttributes {
+                issue: Some(issue),
+                hours: Some(format!("{:.2}", hours)),
+                activity: Some(activity),
+                date: Some(date),
+                comment: None,
+            };
+
+            let mut fields = try!(time_entry_fields(cache, client, &attributes));
+
+            if let Some(owner) = owner {
+                fields.set("user_id", &owner);
+            }
+
+            let entry = try!(client.create_time_entry(&fields));
+            Ok(format!("Created entry {}: {:.2}h on #{} ({}) on {}",
+                       entry.id, entry.hours, issue, entry.activity.name, entry.spent_on))
+        },
+
+        Change::Update { id, hours } => {
+            let mut fields = Fields::time_entry();
+            fields.set("hours", &format!("{:.2}", hours));
+
+            try!(client.update_time_entry(id, &fields));
+            Ok(format!("Updated entry {} to {:.2}h", id, hours))
+        },
+
+        Change::Delete { id } => {
+            try!(client.delete_time_entry(id));
+            Ok(format!("Deleted entry {}", id))
+        },
+
+        Change::Skip(reason) => Ok(format!("Skipped {}", reason)),
+    }
+}
+
+fn time_entry_fields(cache: &mut cache::Cache, client: &client::Client, attributes: &TimeEntryAttributes)
+    -> Result<Fields, resolve::Error>
+{
+    let mut fields = Fields::time_entry();
+
+    if let Some(issue) = attributes.issue {
+        fields.set("issue_id", &issue);
+    }
+
+    if let Some(ref hours) = attributes.hours {
+        fields.set("hours", hours);
+    }
+
+    if let Some(ref activity) = attributes.activity {
+        fields.set("activity_id", &try!(resolve::activity_id(cache, client, activity)));
+    }
+
+    if let Some(ref date) = attributes.date {
+        fields.set("spent_on", date);
+    }
+
+    if let Some(ref comment) = attributes.comment {
+        fields.set("comments", comment);
+    }
+
+    Ok(fields)
+}
+
+/// Asks a yes/no question on the terminal, defaulting to no
+/// How many items to show of a list that was fetched (or cached) in full
+fn max_items(limit: Option<u32>) -> usize {
+    limit.map(|limit| limit as usize).unwrap_or(::std::usize::MAX)
+}
+
+fn confirm(question: &str) -> Result<bool, io::Error> {
+    print!("{} [y/N] ", question);
+    try!(io::stdout().flush());
+
+    let mut answer = String::new();
+    try!(io::stdin().read_line(&mut answer));
+
+    Ok(answer.trim().to_lowercase() == "y")
+}
+
+/// Reduces a file name that came from the server to a name on the current directory, so that
+/// names like `../../.bashrc` can't write anywhere else
+fn local_file_name(name: &str) -> Option<String> {
+    let file_name = Path::new(name).file_name().and_then(|file_name| file_name.to_str());
+
+    match file_name {
+        Some("") | Some(".") | Some("..") | None => None,
+        Some(file_name) => Some(file_name.to_string()),
+    }
+}
+
+fn format_time_entry_line(entry: &TimeEntry) -> String {
+    let issue = entry.issue.as_ref().map(|issue| format!("#{}", issue.id)).unwrap_or("-".to_string());
+    let comments = entry.comments.as_ref().map(|c| &c[..]).unwrap_or("");
+
+    format!("{:<7} {} {:>6.2}h {:<7} {:<14} {:<20} {}",
+            entry.id, entry.spent_on, entry.hours, issue, entry.activity.name, entry.user.name, comments)
+}
+
+/// Prints issues one per line or, when columns or grouping were asked for, as a table
+fn print_issues(issues: &[Issue], options: &IssueListOptions) {
+    let columns = match options.columns {
+        Some(ref columns) => columns,
+        None => {
+            for issue in issues.iter() {
+                println!("{}", format_issue_line(issue));
+            }
+
+            return;
+        },
+    };
+
+    let group_by = options.group_by.as_ref().map(|column| &column[..]);
+    print!("{}", render::issue_table(issues, columns, group_by, terminal::width()));
+}
+
+fn format_issue_line(issue: &Issue) -> String {
+    let assignee = issue.assigned_to.as_ref().map(|user| &user.name[..]).unwrap_or("-");
+
+    format!("#{:<6} {:<14} {:<20} {}", issue.id, issue.status.name, assignee, issue.subject)
 }
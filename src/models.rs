@@ -14,20 +14,304 @@
  * You should have received a copy of the GNU General Public License
  * along with this program; if not, see <http://www.gnu.org/licenses/>. */
 
+use rustc_serialize::{Decodable, Decoder};
+
 #[derive(RustcDecodable, RustcEncodable, Debug, Clone)]
 pub struct IssueStatus {
     id: u32,
     name: String,
+    is_closed: Option<bool>,
 }
 
 impl IssueStatus {
     pub fn into_pair(self) -> (u32, String) { (self.id, self.name) }
+
+    pub fn id(&self) -> u32 { self.id }
+
+    /// Redmine only sends `is_closed` for closed statuses
+    pub fn is_closed(&self) -> bool { self.is_closed.unwrap_or(false) }
 }
 
 #[derive(RustcDecodable, RustcEncodable, Debug, Clone)]
 pub struct User {
-    id: u32,
-    login: String,
-    firstname: String,
-    lastname: String,
+    pub id: u32,
+    pub login: String,
+    pub firstname: String,
+    pub lastname: String,
+}
+
+impl User {
+    pub fn full_name(&self) -> String {
+        format!("{} {}", self.firstname, self.lastname)
+    }
+}
+
+#[derive(RustcDecodable, RustcEncodable, Debug, Clone)]
+pub struct Reference {
+    pub id: u32,
+    pub name: String,
+}
+
+#[derive(RustcDecodable, RustcEncodable, Debug, Clone)]
+pub struct Issue {
+    pub id: u32,
+    pub project: Reference,
+    pub tracker: Reference,
+    pub status: Reference,
+    pub priority: Reference,
+    pub author: Reference,
+    pub assigned_to: Option<Reference>,
+    pub category: Option<Reference>,
+    pub fixed_version: Option<Reference>,
+    pub subject: String,
+    pub description: Option<String>,
+    pub start_date: Option<String>,
+    pub due_date: Option<String>,
+    pub done_ratio: Option<u32>,
+    pub estimated_hours: Option<f64>,
+    pub created_on: String,
+    pub updated_on: String,
+    pub custom_fields: Option<Vec<CustomValue>>,
+
+    // Only present when requested through `include=`
+    pub journals: Option<Vec<Journal>>,
+    pub attachments: Option<Vec<Attachment>>,
+    pub relations: Option<Vec<Relation>>,
+    pub children: Option<Vec<ChildIssue>>,
+    pub watchers: Option<Vec<Reference>>,
+    pub changesets: Option<Vec<Changeset>>,
+}
+
+/// The value of a custom field on an issue (or any other customized object). Redmine sends
+/// multi-value fields as arrays and everything else as a single string; the client normalizes
+/// both into a list.
+#[derive(RustcDecodable, RustcEncodable, Debug, Clone)]
+pub struct CustomValue {
+    pub id: u32,
+    pub name: String,
+    pub value: Vec<String>,
+}
+
+#[derive(RustcDecodable, RustcEncodable, Debug, Clone)]
+pub struct CustomField {
+    pub id: u32,
+    pub name: String,
+    pub customized_type: Option<String>,
+    pub field_format: Option<String>,
+    pub multiple: Option<bool>,
+    pub possible_values: Option<Vec<PossibleValue>>,
+}
+
+impl CustomField {
+    /// A custom field known only by its id, for users that can't list custom fields
+    pub fn from_id(id: u32) -> CustomField {
+        CustomField {
+            id: id,
+            name: format!("cf_{}", id),
+            customized_type: None,
+            field_format: None,
+            multiple: None,
+            possible_values: None,
+        }
+    }
+}
+
+#[derive(RustcDecodable, RustcEncodable, Debug, Clone)]
+pub struct PossibleValue {
+    pub value: String,
+    pub label: Option<String>,
+}
+
+#[derive(RustcDecodable, RustcEncodable, Debug, Clone)]
+pub struct Journal {
+    pub id: u32,
+    pub user: Reference,
+    pub notes: Option<String>,
+    pub private_notes: Option<bool>,
+    pub created_on: String,
+    pub details: Vec<JournalDetail>,
+}
+
+#[derive(RustcDecodable, RustcEncodable, Debug, Clone)]
+pub struct JournalDetail {
+    pub property: String,
+    pub name: String,
+    pub old_value: Option<String>,
+    pub new_value: Option<String>,
+}
+
+#[derive(RustcDecodable, RustcEncodable, Debug, Clone)]
+pub struct Attachment {
+    pub id: u32,
+    pub filename: String,
+    pub filesize: u64,
+    pub content_type: Option<String>,
+    pub description: Option<String>,
+    pub content_url: String,
+    pub author: Reference,
+    pub created_on: String,
+}
+
+#[derive(RustcDecodable, RustcEncodable, Debug, Clone)]
+pub struct Relation {
+    pub id: u32,
+    pub issue_id: u32,
+    pub issue_to_id: u32,
+    pub relation_type: String,
+    pub delay: Option<i32>,
+}
+
+#[derive(RustcDecodable, RustcEncodable, Debug, Clone)]
+pub struct ChildIssue {
+    pub id: u32,
+    pub tracker: Reference,
+    pub subject: String,
+}
+
+#[derive(RustcDecodable, RustcEncodable, Debug, Clone)]
+pub struct Changeset {
+    pub revision: String,
+    pub user: Option<Reference>,
+    pub comments: Option<String>,
+    pub committed_on: String,
+}
+
+/// A reference to an object that Redmine sends without a name (e.g. the issue of a time entry)
+#[derive(RustcDecodable, RustcEncodable, Debug, Clone)]
+pub struct IdReference {
+    pub id: u32,
+}
+
+#[derive(RustcDecodable, RustcEncodable, Debug, Clone)]
+pub struct TimeEntry {
+    pub id: u32,
+    pub project: Reference,
+    pub issue: Option<IdReference>,
+    pub user: Reference,
+    pub activity: Reference,
+    pub hours: f64,
+    pub comments: Option<String>,
+    pub spent_on: String,
+    pub created_on: String,
+    pub updated_on: String,
+}
+
+#[derive(RustcDecodable, RustcEncodable, Debug, Clone)]
+pub struct Project {
+    pub id: u32,
+    pub name: String,
+    pub identifier: String,
+    pub description: Option<String>,
+    pub parent: Option<Reference>,
+    pub is_public: Option<bool>,
+    pub created_on: String,
+    pub updated_on: String,
+
+    // Only present when requested through `include=`
+    pub trackers: Option<Vec<Reference>>,
+    pub issue_categories: Option<Vec<Reference>>,
+    pub enabled_modules: Option<Vec<Reference>>,
+}
+
+#[derive(RustcDecodable, RustcEncodable, Debug, Clone)]
+pub struct Membership {
+    pub id: u32,
+    pub user: Option<Reference>,
+    pub group: Option<Reference>,
+    pub roles: Vec<Reference>,
+}
+
+#[derive(RustcDecodable, RustcEncodable, Debug, Clone)]
+pub struct Version {
+    pub id: u32,
+    pub project: Reference,
+    pub name: String,
+    pub description: Option<String>,
+    pub status: String,
+    pub due_date: Option<String>,
+    pub sharing: Option<String>,
+    pub created_on: String,
+    pub updated_on: String,
+}
+
+/// A query saved on Redmine's web interface. Queries without a project are global.
+#[derive(RustcDecodable, RustcEncodable, Debug, Clone)]
+pub struct Query {
+    pub id: u32,
+    pub name: String,
+    pub is_public: Option<bool>,
+    pub project_id: Option<u32>,
+}
+
+#[derive(RustcDecodable, RustcEncodable, Debug, Clone)]
+pub struct WikiPageReference {
+    pub title: String,
+}
+
+/// An entry of a project's wiki index
+#[derive(RustcDecodable, RustcEncodable, Debug, Clone)]
+pub struct WikiPageSummary {
+    pub title: String,
+    pub parent: Option<WikiPageReference>,
+    pub version: u32,
+    pub created_on: String,
+    pub updated_on: String,
+}
+
+#[derive(RustcDecodable, RustcEncodable, Debug, Clone)]
+pub struct WikiPage {
+    pub title: String,
+    pub parent: Option<WikiPageReference>,
+    pub text: String,
+    pub version: u32,
+    pub author: Option<Reference>,
+    pub comments: Option<String>,
+    pub created_on: String,
+    pub updated_on: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct SearchResult {
+    pub id: u32,
+    pub title: String,
+    pub result_type: String,
+    pub url: String,
+    pub description: Option<String>,
+    pub datetime: String,
+}
+
+// Written by hand because Redmine calls the result type `type`
+impl Decodable for SearchResult {
+    fn decode<D: Decoder>(d: &mut D) -> Result<SearchResult, D::Error> {
+        d.read_struct("SearchResult", 6, |d| {
+            Ok(SearchResult {
+                id: try!(d.read_struct_field("id", 0, Decodable::decode)),
+                title: try!(d.read_struct_field("title", 1, Decodable::decode)),
+                result_type: try!(d.read_struct_field("type", 2, Decodable::decode)),
+                url: try!(d.read_struct_field("url", 3, Decodable::decode)),
+                description: try!(d.read_struct_field("description", 4, Decodable::decode)),
+                datetime: try!(d.read_struct_field("datetime", 5, Decodable::decode)),
+            })
+        })
+    }
+}
+
+/// Objects decoded the way the client would, for tests
+#[cfg(test)]
+pub mod fixtures {
+    use rustc_serialize::json;
+
+    use super::Issue;
+
+    /// An issue with only the attributes Redmine always sends
+    pub fn issue(id: u32, tracker: (u32, &str), status: &str, subject: &str) -> Issue {
+        let (tracker_id, tracker_name) = tracker;
+
+        json::decode(&format!(r#"{{
+            "id": {}, "project": {{ "id": 1, "name": "rdm" }},
+            "tracker": {{ "id": {}, "name": "{}" }}, "status": {{ "id": 1, "name": "{}" }},
+            "priority": {{ "id": 2, "name": "Normal" }}, "author": {{ "id": 1, "name": "John Doe" }},
+            "subject": "{}", "created_on": "2015-04-01T10:00:00Z", "updated_on": "2015-04-01T10:00:00Z"
+        }}"#, id, tracker_id, tracker_name, status, subject)).unwrap()
+    }
 }
@@ -0,0 +1,676 @@
+/* rdm - A command-line redmine client
+ * Copyright (C) 2015 Renato Zannon
+ *
+ * This program is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation; either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program; if not, see <http://www.gnu.org/licenses/>. */
+
+use std::collections::HashMap;
+use std::{cmp, iter};
+use std::fmt::Write;
+
+use cache::Cache;
+use client::Client;
+use models::{Issue, Journal, JournalDetail, Attachment, Changeset, Relation, Project, Membership,
+             Reference, Version, WikiPage, WikiPageSummary, SearchResult, Query};
+
+/// Id-to-name lookups used to turn raw journal values into something readable. Anything that
+/// can't be fetched (e.g. the user list, for non-admins) is simply left out, and the raw ids are
+/// shown instead.
+pub struct Names {
+    statuses: HashMap<u32, String>,
+    users: HashMap<u32, String>,
+    trackers: HashMap<u32, String>,
+}
+
+impl Names {
+    pub fn load(cache: &mut Cache, client: &Client) -> Names {
+        let statuses = cache.issue_statuses(client).unwrap_or(Vec::new());
+        let users    = cache.users(client).unwrap_or(Vec::new());
+        let trackers = cache.trackers(client).unwrap_or(Vec::new());
+
+        Names {
+            statuses: statuses.into_iter().collect(),
+            users: users.into_iter().map(|user| (user.id, user.full_name())).collect(),
+            trackers: trackers.into_iter().map(|tracker| (tracker.id, tracker.name)).collect(),
+        }
+    }
+
+    fn value(&self, field: &str, raw: &str) -> String {
+        let names = match field {
+            "status_id"                    => &self.statuses,
+            "assigned_to_id" | "author_id" => &self.users,
+            "tracker_id"                   => &self.trackers,
+            _ => return raw.to_string(),
+        };
+
+        raw.parse().ok()
+            .and_then(|id: u32| names.get(&id))
+            .map(|name| name.clone())
+            .unwrap_or(raw.to_string())
+    }
+}
+
+enum Event<'a> {
+    Journal(&'a Journal),
+    Attachment(&'a Attachment),
+    Changeset(&'a Changeset),
+}
+
+impl<'a> Event<'a> {
+    fn timestamp(&self) -> &'a str {
+        match *self {
+            Event::Journal(journal)       => &journal.created_on,
+            Event::Attachment(attachment) => &attachment.created_on,
+            Event::Changeset(changeset)   => &changeset.committed_on,
+        }
+    }
+}
+
+pub fn issue_details(issue: &Issue, names: &Names) -> String {
+    let mut out = String::new();
+
+    writeln!(out, "#{} {}: {}", issue.id, issue.tracker.name, issue.subject).unwrap();
+    writeln!(out, "").unwrap();
+
+    let assignee = issue.assigned_to.as_ref().map(|user| &user.name[..]).unwrap_or("-");
+    let estimated = issue.estimated_hours.map(|hours| format!("{}h", hours));
+
+    field_pair(&mut out, "Project", &issue.project.name, "Status", &issue.status.name);
+    field_pair(&mut out, "Author", &issue.author.name, "Assignee", assignee);
+    field_pair(&mut out, "Priority", &issue.priority.name,
+               "Done", &format!("{}%", issue.done_ratio.unwrap_or(0)));
+    field_pair(&mut out, "Start", or_dash(&issue.start_date), "Due", or_dash(&issue.due_date));
+    field_pair(&mut out, "Created", &format_timestamp(&issue.created_on),
+               "Updated", &format_timestamp(&issue.updated_on));
+    field_pair(&mut out, "Estimated", or_dash(&estimated), "", "");
+
+    for field in issue.custom_fields.iter().flat_map(|fields| fields.iter()) {
+        let value = if field.value.is_empty() { "-".to_string() } else { field.value.connect(", ") };
+        writeln!(out, "{:<11}{}", format!("{}:", field.name), value).unwrap();
+    }
+
+    if let Some(ref description) = issue.description {
+        if !description.trim().is_empty() {
+            writeln!(out, "\nDescription:").unwrap();
+            indented(&mut out, description);
+        }
+    }
+
+    if let Some(ref children) = issue.children {
+        if !children.is_empty() {
+            writeln!(out, "\nSubtasks:").unwrap();
+
+            for child in children.iter() {
+                writeln!(out, "    #{} {}: {}", child.id, child.tracker.name, child.subject).unwrap();
+            }
+        }
+    }
+
+    if let Some(ref relations) = issue.relations {
+        if !relations.is_empty() {
+            writeln!(out, "\nRelated issues:").unwrap();
+
+            for relation in relations.iter() {
+                writeln!(out, "    {}", relation_summary(relation, issue.id)).unwrap();
+            }
+        }
+    }
+
+    if let Some(ref watchers) = issue.watchers {
+        if !watchers.is_empty() {
+            let watcher_names: Vec<&str> = watchers.iter().map(|w| &w.name[..]).collect();
+            writeln!(out, "\nWatchers: {}", watcher_names.connect(", ")).unwrap();
+        }
+    }
+
+    let mut events: Vec<Event> = Vec::new();
+    events.extend(issue.journals.iter().flat_map(|js| js.iter()).map(Event::Journal));
+    events.extend(issue.attachments.iter().flat_map(|atts| atts.iter()).map(Event::Attachment));
+    events.extend(issue.changesets.iter().flat_map(|css| css.iter()).map(Event::Changeset));
+
+    // Redmine timestamps are ISO 8601, so they sort correctly as strings
+    events.sort_by(|a, b| a.timestamp().cmp(b.timestamp()));
+
+    if !events.is_empty() {
+        writeln!(out, "\nHistory:").unwrap();
+    }
+
+    for event in events.iter() {
+        writeln!(out, "").unwrap();
+        history_entry(&mut out, event, issue, names);
+    }
+
+    out
+}
+
+/// Lists projects as a tree, with subprojects indented under their parents
+pub fn project_tree(projects: &[Project]) -> String {
+    let mut out = String::new();
+
+    // Projects whose parent isn't visible are shown at the top level
+    let roots = projects.iter().filter(|project| {
+        project.parent.as_ref().map(|parent| !projects.iter().any(|p| p.id == parent.id)).unwrap_or(true)
+    });
+
+    for root in roots {
+        project_subtree(&mut out, root, projects, 0);
+    }
+
+    out
+}
+
+fn project_subtree(out: &mut String, project: &Project, projects: &[Project], depth: usize) {
+    let indentation: String = (0..depth).map(|_| "  ").collect();
+    writeln!(out, "{}{} ({})", indentation, project.name, project.identifier).unwrap();
+
+    let children = projects.iter().filter(|p| p.parent.as_ref().map(|parent| parent.id) == Some(project.id));
+
+    for child in children {
+        project_subtree(out, child, projects, depth + 1);
+    }
+}
+
+pub fn project_details(project: &Project, memberships: &[Membership]) -> String {
+    let mut out = String::new();
+
+    writeln!(out, "{} ({})", project.name, project.identifier).unwrap();
+    writeln!(out, "").unwrap();
+
+    let visibility = match project.is_public {
+        Some(false) => "private",
+        _           => "public",
+    };
+
+    let parent = project.parent.as_ref().map(|parent| &parent.name[..]).unwrap_or("-");
+
+    field_pair(&mut out, "Parent", parent, "Visibility", visibility);
+    field_pair(&mut out, "Created", &format_timestamp(&project.created_on),
+               "Updated", &format_timestamp(&project.updated_on));
+
+    if let Some(ref description) = project.description {
+        if !description.trim().is_empty() {
+            writeln!(out, "\nDescription:").unwrap();
+            indented(&mut out, description);
+        }
+    }
+
+    writeln!(out, "").unwrap();
+    name_list(&mut out, "Trackers", &project.trackers);
+    name_list(&mut out, "Categories", &project.issue_categories);
+    name_list(&mut out, "Modules", &project.enabled_modules);
+
+    if !memberships.is_empty() {
+        writeln!(out, "\nMembers:").unwrap();
+
+        for membership in memberships.iter() {
+            let member = membership.user.as_ref().or(membership.group.as_ref())
+                .map(|member| &member.name[..])
+                .unwrap_or("?");
+
+            let roles: Vec<&str> = membership.roles.iter().map(|role| &role.name[..]).collect();
+            writeln!(out, "    {:<30}{}", member, roles.connect(", ")).unwrap();
+        }
+    }
+
+    out
+}
+
+fn name_list(out: &mut String, label: &str, references: &Option<Vec<Reference>>) {
+    if let Some(ref references) = *references {
+        let names: Vec<&str> = references.iter().map(|reference| &reference.name[..]).collect();
+        writeln!(out, "{:<11}{}", format!("{}:", label), if names.is_empty() { "-".to_string() } else { names.connect(", ") }).unwrap();
+    }
+}
+
+pub fn version_line(version: &Version, open: u64, closed: u64) -> String {
+    format!("{:<6} {:<20} {:<8} {:<11} {} {}/{} closed", version.id, version.name, version.status,
+            or_dash(&version.due_date),
+            progress_bar(closed, open + closed, 20), closed, open + closed)
+}
+
+pub fn version_details(version: &Version, open: u64, closed: u64) -> String {
+    let mut out = String::new();
+
+    writeln!(out, "{} ({})", version.name, version.project.name).unwrap();
+    writeln!(out, "").unwrap();
+
+    field_pair(&mut out, "Status", &version.status, "Due", or_dash(&version.due_date));
+    field_pair(&mut out, "Sharing", or_dash(&version.sharing), "Updated", &format_timestamp(&version.updated_on));
+    field_pair(&mut out, "Issues", &format!("{} open, {} closed", open, closed), "", "");
+    writeln!(out, "{:<11}{}", "Progress:", progress_bar(closed, open + closed, 40)).unwrap();
+
+    if let Some(ref description) = version.description {
+        if !description.trim().is_empty() {
+            writeln!(out, "\nDescription:").unwrap();
+            indented(&mut out, description);
+        }
+    }
+
+    out
+}
+
+/// Draws e.g. `[#####---------------]  25%`
+pub fn progress_bar(done: u64, total: u64, width: usize) -> String {
+    let (filled, percent) = if total == 0 {
+        (0, 0)
+    } else {
+        ((done * width as u64 / total) as usize, done * 100 / total)
+    };
+
+    let bar: String = iter::repeat("#").take(filled).chain(iter::repeat("-").take(width - filled)).collect();
+    format!("[{}] {:>3}%", bar, percent)
+}
+
+/// Lists wiki pages as a tree, with child pages indented under their parents
+pub fn wiki_index(pages: &[WikiPageSummary]) -> String {
+    let mut out = String::new();
+
+    let roots = pages.iter().filter(|page| {
+        page.parent.as_ref().map(|parent| !pages.iter().any(|p| p.title == parent.title)).unwrap_or(true)
+    });
+
+    for root in roots {
+        wiki_subtree(&mut out, root, pages, 0);
+    }
+
+    out
+}
+
+fn wiki_subtree(out: &mut String, page: &WikiPageSummary, pages: &[WikiPageSummary], depth: usize) {
+    let indentation: String = (0..depth).map(|_| "  ").collect();
+    writeln!(out, "{}{} (v{}, {})", indentation, page.title, page.version, format_timestamp(&page.updated_on)).unwrap();
+
+    let children = pages.iter().filter(|p| p.parent.as_ref().map(|parent| &parent.title) == Some(&page.title));
+
+    for child in children {
+        wiki_subtree(out, child, pages, depth + 1);
+    }
+}
+
+pub fn wiki_page(page: &WikiPage) -> String {
+    let mut out = String::new();
+
+    writeln!(out, "{}", page.title).unwrap();
+    writeln!(out, "").unwrap();
+    writeln!(out, "{}", wiki_version_line(page)).unwrap();
+    writeln!(out, "").unwrap();
+    writeln!(out, "{}", page.text.trim_right()).unwrap();
+
+    out
+}
+
+/// Describes who changed a wiki page version, and when
+pub fn wiki_version_line(page: &WikiPage) -> String {
+    let author = page.author.as_ref().map(|author| &author.name[..]).unwrap_or("?");
+
+    format!("v{:<5} {} {:<20} {}", page.version, format_timestamp(&page.updated_on), author,
+            or_dash(&page.comments))
+}
+
+pub fn search_result(result: &SearchResult) -> String {
+    format!("{:<14} {} {}\n    {}", result.result_type, format_timestamp(&result.datetime), result.title,
+            result.url)
+}
+
+pub fn query_line(query: &Query, projects: &[Project]) -> String {
+    let project = match query.project_id {
+        Some(id) => projects.iter().find(|project| project.id == id)
+            .map(|project| project.name.clone())
+            .unwrap_or(format!("project {}", id)),
+        None => "(all projects)".to_string(),
+    };
+
+    let visibility = match query.is_public {
+        Some(true) => "public",
+        _          => "private",
+    };
+
+    format!("{:<6} {:<30} {:<8} {}", query.id, query.name, visibility, project)
+}
+
+/// Lays issues out as an aligned table, optionally split into groups by the value of one column.
+/// When the table is wider than `width`, the subject column (or else the last one) is cut short.
+pub fn issue_table(issues: &[Issue], columns: &[String], group_by: Option<&str>, width: Option<usize>) -> String {
+    let headers: Vec<String> = columns.iter().map(|column| field_label(column)).collect();
+    let rows: Vec<Vec<String>> = issues.iter()
+        .map(|issue| columns.iter().map(|column| issue_column(issue, column)).collect())
+        .collect();
+
+    let mut widths: Vec<usize> = headers.iter().map(|header| header.chars().count()).collect();
+
+    for row in rows.iter() {
+        for (index, cell) in row.iter().enumerate() {
+            widths[index] = cmp::max(widths[index], cell.chars().count());
+        }
+    }
+
+    if let Some(width) = width {
+        let total = widths.iter().fold(0, |sum, width| sum + width) + 2 * (widths.len() - 1);
+
+        if total > width {
+            let shrunk = columns.iter().position(|column| *column == "subject").unwrap_or(columns.len() - 1);
+            let others = total - widths[shrunk];
+
+            widths[shrunk] = cmp::max(width.saturating_sub(others), 10);
+        }
+    }
+
+    let mut out = String::new();
+    table_row(&mut out, &headers, &widths);
+
+    let column = match group_by {
+        Some(column) => column,
+        None => {
+            for row in rows.iter() {
+                table_row(&mut out, row, &widths);
+            }
+
+            return out;
+        }
+    };
+
+    // Groups keep the order in which they first show up, so that sorting on the grouped column
+    // also sorts the groups
+    let mut groups: Vec<(String, Vec<&Vec<String>>)> = Vec::new();
+
+    for (issue, row) in issues.iter().zip(rows.iter()) {
+        let key = issue_column(issue, column);
+        let position = groups.iter().position(|&(ref group, _)| *group == key);
+
+        match position {
+            Some(index) => groups[index].1.push(row),
+            None        => groups.push((key, vec![row])),
+        }
+    }
+
+    for (key, rows) in groups.into_iter() {
+        writeln!(out, "\n{}: {} ({})", field_label(column), key, rows.len()).unwrap();
+
+        for row in rows.into_iter() {
+            table_row(&mut out, row, &widths);
+        }
+    }
+
+    out
+}
+
+/// The value of one of `args::ISSUE_COLUMNS` for `issue`
+fn issue_column(issue: &Issue, column: &str) -> String {
+    let name = |reference: &Option<Reference>| {
+        reference.as_ref().map(|reference| reference.name.clone()).unwrap_or("-".to_string())
+    };
+
+    match column {
+        "id"              => format!("#{}", issue.id),
+        "project"         => issue.project.name.clone(),
+        "tracker"         => issue.tracker.name.clone(),
+        "status"          => issue.status.name.clone(),
+        "priority"        => issue.priority.name.clone(),
+        "author"          => issue.author.name.clone(),
+        "assignee"        => name(&issue.assigned_to),
+        "subject"         => issue.subject.clone(),
+        "version"         => name(&issue.fixed_version),
+        "category"        => name(&issue.category),
+        "start_date"      => or_dash(&issue.start_date).to_string(),
+        "due_date"        => or_dash(&issue.due_date).to_string(),
+        "done_ratio"      => issue.done_ratio.map(|ratio| format!("{}%", ratio)).unwrap_or("-".to_string()),
+        "estimated_hours" => issue.estimated_hours.map(|hours| format!("{:.2}", hours)).unwrap_or("-".to_string()),
+        "created_on"      => format_timestamp(&issue.created_on),
+        "updated_on"      => format_timestamp(&issue.updated_on),
+        _                 => String::new(),
+    }
+}
+
+fn table_row(out: &mut String, cells: &[String], widths: &[usize]) {
+    let last = cells.len() - 1;
+
+    let fitted: Vec<String> = cells.iter().zip(widths.iter()).enumerate().map(|(index, (cell, &width))| {
+        let cell = truncate(cell, width);
+
+        // The last cell isn't padded, to keep trailing whitespace out of the output
+        if index == last { cell } else { format!("{:<1$}", cell, width) }
+    }).collect();
+
+    writeln!(out, "{}", fitted.connect("  ")).unwrap();
+}
+
+fn truncate(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        text.to_string()
+    } else if width <= 3 {
+        text.chars().take(width).collect()
+    } else {
+        text.chars().take(width - 3).chain("...".chars()).collect()
+    }
+}
+
+/// Describes `relation` from the point of view of issue `number`, e.g. "blocked by #12"
+pub fn relation_summary(relation: &Relation, number: u32) -> String {
+    let (relation_type, other) = if relation.issue_id == number {
+        (&relation.relation_type[..], relation.issue_to_id)
+    } else {
+        (inverse_relation(&relation.relation_type), relation.issue_id)
+    };
+
+    let description = match relation_type {
+        "relates"     => "related to",
+        "duplicated"  => "duplicated by",
+        "blocked"     => "blocked by",
+        "copied_to"   => "copied to",
+        "copied_from" => "copied from",
+        other         => other,
+    };
+
+    match relation.delay {
+        Some(delay) if delay != 0 => format!("{} #{} (delay: {} days)", description, other, delay),
+        _                         => format!("{} #{}", description, other),
+    }
+}
+
+fn inverse_relation(relation_type: &str) -> &str {
+    match relation_type {
+        "duplicates"  => "duplicated",
+        "duplicated"  => "duplicates",
+        "blocks"      => "blocked",
+        "blocked"     => "blocks",
+        "precedes"    => "follows",
+        "follows"     => "precedes",
+        "copied_to"   => "copied_from",
+        "copied_from" => "copied_to",
+        other         => other,
+    }
+}
+
+fn history_entry(out: &mut String, event: &Event, issue: &Issue, names: &Names) {
+    let when = format_timestamp(event.timestamp());
+
+    match *event {
+        Event::Journal(journal) => {
+            let private = if journal.private_notes.unwrap_or(false) { " (private)" } else { "" };
+            writeln!(out, "  {} - {}{}", when, journal.user.name, private).unwrap();
+
+            for detail in journal.details.iter() {
+                writeln!(out, "    * {}", describe_detail(detail, issue, names)).unwrap();
+            }
+
+            if let Some(ref notes) = journal.notes {
+                if !notes.trim().is_empty() {
+                    indented(out, notes);
+                }
+            }
+        },
+
+        Event::Attachment(attachment) => {
+            writeln!(out, "  {} - {}", when, attachment.author.name).unwrap();
+            writeln!(out, "    * Attached {} ({} bytes, attachment #{})",
+                     attachment.filename, attachment.filesize, attachment.id).unwrap();
+        },
+
+        Event::Changeset(changeset) => {
+            let author = changeset.user.as_ref().map(|user| &user.name[..]).unwrap_or("unknown");
+            writeln!(out, "  {} - {}", when, author).unwrap();
+            writeln!(out, "    * Revision {}", changeset.revision).unwrap();
+
+            if let Some(ref comments) = changeset.comments {
+                indented(out, comments);
+            }
+        },
+    }
+}
+
+fn describe_detail(detail: &JournalDetail, issue: &Issue, names: &Names) -> String {
+    let (label, old, new) = match &detail.property[..] {
+        "attr" if detail.name == "description" => {
+            return "Description updated".to_string();
+        },
+
+        "attr" => {
+            let value = |raw: &String| names.value(&detail.name, raw);
+            (field_label(&detail.name), detail.old_value.as_ref().map(&value), detail.new_value.as_ref().map(&value))
+        },
+
+        "cf" => {
+            let label = issue.custom_fields.iter().flat_map(|fields| fields.iter())
+                .find(|field| field.id.to_string() == detail.name)
+                .map(|field| field.name.clone())
+                .unwrap_or(format!("Custom field #{}", detail.name));
+
+            (label, detail.old_value.clone(), detail.new_value.clone())
+        },
+
+        "attachment" => {
+            return match (&detail.old_value, &detail.new_value) {
+                (_, &Some(ref file)) => format!("File {} added", file),
+                (&Some(ref file), _) => format!("File {} deleted", file),
+                _                    => format!("Attachment #{} changed", detail.name),
+            };
+        },
+
+        "relation" => {
+            return match (&detail.old_value, &detail.new_value) {
+                (_, &Some(ref other)) => format!("Relation {} #{} added", detail.name, other),
+                (&Some(ref other), _) => format!("Relation {} #{} deleted", detail.name, other),
+                _                     => format!("Relation {} changed", detail.name),
+            };
+        },
+
+        _ => (format!("{} {}", detail.property, detail.name), detail.old_value.clone(), detail.new_value.clone()),
+    };
+
+    match (old, new) {
+        (Some(old), Some(new)) => format!("{} changed from {} to {}", label, old, new),
+        (None, Some(new))      => format!("{} set to {}", label, new),
+        (Some(old), None)      => format!("{} deleted ({})", label, old),
+        (None, None)           => format!("{} changed", label),
+    }
+}
+
+/// Turns an attribute name such as `assigned_to_id` into `Assigned to`
+fn field_label(name: &str) -> String {
+    let name = if name.ends_with("_id") { &name[..name.len() - 3] } else { name };
+    let words = name.replace("_", " ");
+
+    let mut chars = words.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None        => String::new(),
+    }
+}
+
+fn field_pair(out: &mut String, label_a: &str, value_a: &str, label_b: &str, value_b: &str) {
+    let label_a = format!("{}:", label_a);
+
+    if label_b.is_empty() {
+        writeln!(out, "{:<11}{}", label_a, value_a).unwrap();
+    } else {
+        let label_b = format!("{}:", label_b);
+        writeln!(out, "{:<11}{:<24}{:<11}{}", label_a, value_a, label_b, value_b).unwrap();
+    }
+}
+
+fn indented(out: &mut String, text: &str) {
+    for line in text.lines() {
+        writeln!(out, "    {}", line).unwrap();
+    }
+}
+
+fn or_dash(value: &Option<String>) -> &str {
+    value.as_ref().map(|v| &v[..]).unwrap_or("-")
+}
+
+/// Formats `2015-04-01T12:00:00Z` as `2015-04-01 12:00`
+pub fn format_timestamp(timestamp: &str) -> String {
+    let readable = timestamp.replace("T", " ");
+    readable.chars().take(16).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use models::Issue;
+    use models::fixtures;
+    use super::{issue_table, truncate};
+
+    fn issue(id: u32, status: &str, subject: &str) -> Issue {
+        fixtures::issue(id, (1, "Bug"), status, subject)
+    }
+
+    fn columns(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn short_text_is_kept() {
+        assert_eq!(truncate("hello", 5), "hello");
+        assert_eq!(truncate("hello", 10), "hello");
+    }
+
+    #[test]
+    fn long_text_is_cut_with_an_ellipsis() {
+        assert_eq!(truncate("hello world", 8), "hello...");
+        assert_eq!(truncate("hello", 2), "he");
+        assert_eq!(truncate("ação rápida", 7), "ação...");
+    }
+
+    #[test]
+    fn tables_are_aligned() {
+        let issues = vec![issue(3, "New", "Leak"), issue(12, "In Progress", "Crash on start")];
+        let table = issue_table(&issues, &columns(&["id", "status", "subject"]), None, None);
+
+        assert_eq!(table, "Id   Status       Subject\n\
+                           #3   New          Leak\n\
+                           #12  In Progress  Crash on start\n");
+    }
+
+    #[test]
+    fn the_subject_shrinks_to_fit() {
+        let issues = vec![issue(3, "New", "Leak"), issue(12, "In Progress", "Crash on start")];
+        let table = issue_table(&issues, &columns(&["id", "status", "subject"]), None, Some(30));
+
+        assert_eq!(table.lines().last(), Some("#12  In Progress  Crash on ..."));
+    }
+
+    #[test]
+    fn groups_keep_their_first_order() {
+        let issues = vec![issue(3, "New", "Leak"), issue(12, "In Progress", "Crash"), issue(4, "New", "Typo")];
+        let table = issue_table(&issues, &columns(&["id", "subject"]), Some("status"), None);
+
+        assert_eq!(table, "Id   Subject\n\
+                           \n\
+                           Status: New (2)\n\
+                           #3   Leak\n\
+                           #4   Typo\n\
+                           \n\
+                           Status: In Progress (1)\n\
+                           #12  Crash\n");
+    }
+}
@@ -0,0 +1,298 @@
+/* rdm - A command-line redmine client
+ * Copyright (C) 2015 Renato Zannon
+ *
+ * This program is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation; either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program; if not, see <http://www.gnu.org/licenses/>. */
+
+use std::{fmt, error};
+
+use cache::Cache;
+use client::{self, Client};
+use models::{User, Reference, CustomField, Project, Query};
+
+#[derive(Debug)]
+pub enum Error {
+    NoMatch { kind: &'static str, query: String },
+    Ambiguous { kind: &'static str, query: String, candidates: Vec<String> },
+    NoProject { kind: &'static str },
+    Client(client::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            Error::NoMatch { kind, ref query } => {
+                write!(f, "No {} matched '{}'", kind, query)
+            },
+
+            Error::Ambiguous { kind, ref query, ref candidates } => {
+                try!(writeln!(f, "'{}' matches more than one {}:", query, kind));
+
+                for candidate in candidates.iter() {
+                    try!(writeln!(f, "    {}", candidate));
+                }
+
+                Ok(())
+            },
+
+            Error::NoProject { kind } => {
+                write!(f, "A project is needed to look up a {} by name", kind)
+            },
+
+            Error::Client(ref err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn description(&self) -> &str {
+        match *self {
+            Error::NoMatch { .. }   => "No match for name",
+            Error::Ambiguous { .. } => "Ambiguous name",
+            Error::NoProject { .. } => "Project-specific name used without a project",
+            Error::Client(_)        => "Error fetching names from the server",
+        }
+    }
+
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            Error::Client(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<client::Error> for Error {
+    fn from(err: client::Error) -> Error {
+        Error::Client(err)
+    }
+}
+
+/// Finds the first issue status whose name starts with `query`, ignoring case
+pub fn status_id(cache: &mut Cache, client: &Client, query: &str) -> Result<u32, Error> {
+    let statuses = try!(cache.issue_statuses(client));
+
+    let status = statuses.into_iter().filter_map(|(id, name)| {
+        let matches = query.chars().zip(name.chars()).all(|(query_chr, name_chr)| {
+            query_chr.to_lowercase().zip(name_chr.to_lowercase()).all(|(a, b)| a == b)
+        });
+
+        if matches {
+            Some(id)
+        } else {
+            None
+        }
+    }).next();
+
+    match status {
+        Some(status) => Ok(status),
+        None => Err(Error::NoMatch { kind: "issue status", query: query.to_string() }),
+    }
+}
+
+/// Resolves a user by login, first name, last name or full name. Exact (case-insensitive)
+/// matches win over partial ones; `me` is the user that owns the configured API key.
+pub fn user(cache: &mut Cache, client: &Client, query: &str) -> Result<User, Error> {
+    if query == "me" {
+        return Ok(try!(client.current_user()));
+    }
+
+    let users = try!(cache.users(client));
+
+    if let Ok(id) = query.parse::<u32>() {
+        if let Some(user) = users.iter().find(|user| user.id == id) {
+            return Ok(user.clone());
+        }
+    }
+
+    let user = try!(find_by_name("user", query, &users, |user| {
+        vec![
+            format!("{} ({})", user.full_name(), user.login),
+            user.login.clone(),
+            user.firstname.clone(),
+            user.lastname.clone(),
+            user.full_name(),
+        ]
+    }));
+
+    Ok(user.clone())
+}
+
+/// Finds the single item that `query` names. `names` lists every name an item can be matched
+/// by; its first entry is the one shown to the user when the query is ambiguous.
+pub fn find_by_name<'a, T, F>(kind: &'static str, query: &str, items: &'a [T], names: F)
+    -> Result<&'a T, Error> where F: Fn(&T) -> Vec<String>
+{
+    let lowercase_query = query.to_lowercase();
+
+    let exact: Vec<&T> = items.iter().filter(|item| {
+        names(item).iter().any(|name| name.to_lowercase() == lowercase_query)
+    }).collect();
+
+    let matches = if exact.is_empty() {
+        items.iter().filter(|item| {
+            names(item).iter().any(|name| name.to_lowercase().contains(&lowercase_query[..]))
+        }).collect()
+    } else {
+        exact
+    };
+
+    match matches.len() {
+        0 => Err(Error::NoMatch { kind: kind, query: query.to_string() }),
+        1 => Ok(matches[0]),
+        _ => Err(Error::Ambiguous {
+            kind: kind,
+            query: query.to_string(),
+            candidates: matches.iter().map(|item| names(item).swap_remove(0)).collect(),
+        }),
+    }
+}
+
+/// Resolves a project by identifier, id or name
+pub fn project(cache: &mut Cache, client: &Client, query: &str) -> Result<Project, Error> {
+    let projects = try!(cache.projects(client));
+
+    if let Some(project) = projects.iter().find(|p| p.identifier == query || p.id.to_string() == query) {
+        return Ok(project.clone());
+    }
+
+    let project = try!(find_by_name("project", query, &projects, |project| {
+        vec![format!("{} ({})", project.name, project.identifier), project.name.clone(), project.identifier.clone()]
+    }));
+
+    Ok(project.clone())
+}
+
+/// Resolves a saved query by id or name. The queries are cached, so on no match they're fetched
+/// again once, in case the query was saved after the cache was filled.
+pub fn query(cache: &mut Cache, client: &Client, query: &str) -> Result<Query, Error> {
+    match find_query(cache, client, query) {
+        Err(Error::NoMatch { .. }) => {
+            cache.forget_queries();
+            find_query(cache, client, query)
+        },
+        result => result,
+    }
+}
+
+fn find_query(cache: &mut Cache, client: &Client, query: &str) -> Result<Query, Error> {
+    let queries = try!(cache.queries(client));
+
+    if let Some(saved) = queries.iter().find(|saved| saved.id.to_string() == query) {
+        return Ok(saved.clone());
+    }
+
+    let saved = try!(find_by_name("query", query, &queries, |saved| vec![saved.name.clone()]));
+    Ok(saved.clone())
+}
+
+pub fn tracker_id(cache: &mut Cache, client: &Client, query: &str) -> Result<u32, Error> {
+    let trackers = try!(cache.trackers(client));
+    reference_id("tracker", query, &trackers)
+}
+
+pub fn priority_id(cache: &mut Cache, client: &Client, query: &str) -> Result<u32, Error> {
+    let priorities = try!(cache.issue_priorities(client));
+    reference_id("priority", query, &priorities)
+}
+
+pub fn activity_id(cache: &mut Cache, client: &Client, query: &str) -> Result<u32, Error> {
+    let activities = try!(cache.time_entry_activities(client));
+    reference_id("activity", query, &activities)
+}
+
+pub fn version_id(cache: &mut Cache, client: &Client, project: &str, query: &str) -> Result<u32, Error> {
+    let versions = try!(cache.versions(client, project));
+
+    if let Some(version) = versions.iter().find(|version| version.id.to_string() == query) {
+        return Ok(version.id);
+    }
+
+    let version = try!(find_by_name("version", query, &versions, |v| vec![v.name.clone()]));
+    Ok(version.id)
+}
+
+pub fn category_id(cache: &mut Cache, client: &Client, project: &str, query: &str) -> Result<u32, Error> {
+    let categories = try!(cache.issue_categories(client, project));
+    reference_id("category", query, &categories)
+}
+
+fn reference_id(kind: &'static str, query: &str, references: &[Reference]) -> Result<u32, Error> {
+    if let Ok(id) = query.parse::<u32>() {
+        if references.iter().any(|reference| reference.id == id) {
+            return Ok(id);
+        }
+    }
+
+    let reference = try!(find_by_name(kind, query, references, |r| vec![r.name.clone()]));
+    Ok(reference.id)
+}
+
+/// Resolves a custom field by name, id or `cf_<id>`. Only administrators can list custom fields;
+/// for everyone else, fields can only be referred to by id.
+pub fn custom_field(cache: &mut Cache, client: &Client, query: &str) -> Result<CustomField, Error> {
+    let definitions = try!(cache.custom_fields(client));
+
+    let id_query = if query.starts_with("cf_") { &query[3..] } else { query };
+
+    if let Ok(id) = id_query.parse::<u32>() {
+        let definition = definitions.into_iter().find(|field| field.id == id);
+        return Ok(definition.unwrap_or(CustomField::from_id(id)));
+    }
+
+    let issue_fields: Vec<CustomField> = definitions.into_iter().filter(|field| {
+        field.customized_type.as_ref().map(|t| t == "issue").unwrap_or(true)
+    }).collect();
+
+    if issue_fields.is_empty() {
+        return Err(Error::NoMatch { kind: "custom field (try its id)", query: query.to_string() });
+    }
+
+    let field = try!(find_by_name("custom field", query, &issue_fields, |f| vec![f.name.clone()]));
+    Ok(field.clone())
+}
+
+/// Resolves `name=value` pairs into custom fields and their values. Repeated names are collected
+/// into a single multi-value entry, and values of list fields are matched against the field's
+/// possible values.
+pub fn custom_values(cache: &mut Cache, client: &Client, pairs: &[(String, String)])
+    -> Result<Vec<(CustomField, Vec<String>)>, Error>
+{
+    let mut resolved: Vec<(CustomField, Vec<String>)> = Vec::new();
+
+    for &(ref name, ref value) in pairs.iter() {
+        let field = try!(custom_field(cache, client, name));
+
+        let value = match field.possible_values {
+            Some(ref possible) if !value.is_empty() => {
+                let kind = "custom field value";
+                let matched = try!(find_by_name(kind, value, possible, |p| {
+                    let mut names = vec![p.label.clone().unwrap_or(p.value.clone())];
+                    names.push(p.value.clone());
+                    names
+                }));
+
+                matched.value.clone()
+            },
+
+            _ => value.clone(),
+        };
+
+        match resolved.iter().position(|&(ref f, _)| f.id == field.id) {
+            Some(index) => resolved[index].1.push(value),
+            None        => resolved.push((field, vec![value])),
+        }
+    }
+
+    Ok(resolved)
+}
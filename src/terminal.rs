@@ -0,0 +1,41 @@
+/* rdm - A command-line redmine client
+ * Copyright (C) 2015 Renato Zannon
+ *
+ * This program is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation; either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program; if not, see <http://www.gnu.org/licenses/>. */
+
+use std::env;
+use std::process::{Command, Stdio};
+
+use libc;
+
+/// The width of the terminal, in columns. None when stdout isn't a terminal (e.g. when piped),
+/// so that redirected output is never truncated. Otherwise `$COLUMNS` wins when set, and `stty`
+/// is asked about the terminal on stdin as a fallback.
+pub fn width() -> Option<usize> {
+    if unsafe { libc::isatty(1) } == 0 {
+        return None;
+    }
+
+    if let Some(columns) = env::var("COLUMNS").ok().and_then(|columns| columns.parse().ok()) {
+        return Some(columns);
+    }
+
+    let output = match Command::new("stty").arg("size").stdin(Stdio::inherit()).output() {
+        Ok(ref output) if output.status.success() => String::from_utf8_lossy(&output.stdout).into_owned(),
+        _ => return None,
+    };
+
+    // `stty size` prints "<rows> <columns>"
+    output.split_whitespace().nth(1).and_then(|columns| columns.parse().ok())
+}
@@ -0,0 +1,160 @@
+/* rdm - A command-line redmine client
+ * Copyright (C) 2015 Renato Zannon
+ *
+ * This program is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation; either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program; if not, see <http://www.gnu.org/licenses/>. */
+
+use std::io::prelude::*;
+use std::io::{self, BufReader};
+use std::fs::{self, File};
+use std::path::PathBuf;
+use std::{error, fmt};
+
+use rustc_serialize::json;
+use time;
+
+use user_config::Config;
+
+/// A timer that is running on an issue. It is kept on `.rdm-timer.json`, next to the config file,
+/// so that it outlives the terminal it was started on.
+#[derive(RustcDecodable, RustcEncodable, Debug, Clone)]
+pub struct Timer {
+    pub issue: u32,
+    pub started_at: i64,
+    pub activity: Option<String>,
+    pub comment: Option<String>,
+}
+
+impl Timer {
+    pub fn start(issue: u32, activity: Option<String>, comment: Option<String>) -> Timer {
+        Timer {
+            issue: issue,
+            started_at: time::get_time().sec,
+            activity: activity,
+            comment: comment,
+        }
+    }
+
+    pub fn elapsed_seconds(&self) -> i64 {
+        time::get_time().sec - self.started_at
+    }
+
+    /// The day the timer was started, as YYYY-MM-DD
+    pub fn start_date(&self) -> String {
+        let started = time::at(time::Timespec::new(self.started_at, 0));
+        time::strftime("%Y-%m-%d", &started).unwrap()
+    }
+
+    /// The time the timer was started, as YYYY-MM-DD HH:MM
+    pub fn start_time(&self) -> String {
+        let started = time::at(time::Timespec::new(self.started_at, 0));
+        time::strftime("%Y-%m-%d %H:%M", &started).unwrap()
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum Rounding {
+    Up,
+    Down,
+    Nearest,
+}
+
+/// Rounds the elapsed time to a multiple of `interval` minutes, returning the rounded minutes
+pub fn round_minutes(elapsed_seconds: i64, interval: u32, rounding: Rounding) -> u32 {
+    let interval = if interval == 0 { 1 } else { interval as i64 } * 60;
+    let elapsed = if elapsed_seconds < 0 { 0 } else { elapsed_seconds };
+
+    let intervals = match rounding {
+        Rounding::Up      => (elapsed + interval - 1) / interval,
+        Rounding::Down    => elapsed / interval,
+        Rounding::Nearest => (elapsed + interval / 2) / interval,
+    };
+
+    (intervals * interval / 60) as u32
+}
+
+/// Formats a number of seconds as H:MM
+pub fn format_duration(seconds: i64) -> String {
+    let minutes = if seconds < 0 { 0 } else { seconds / 60 };
+    format!("{}:{:02}", minutes / 60, minutes % 60)
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    Parsing(json::DecoderError),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            Error::Io(ref err)      => write!(f, "Timer error: {}", err),
+            Error::Parsing(ref err) => write!(f, "Timer error: invalid timer file: {}", err),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn description(&self) -> &str {
+        match *self {
+            Error::Io(_)      => "timer error: error accessing the timer file",
+            Error::Parsing(_) => "timer error: syntax error on timer file",
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        Error::Io(err)
+    }
+}
+
+impl From<json::DecoderError> for Error {
+    fn from(err: json::DecoderError) -> Error {
+        Error::Parsing(err)
+    }
+}
+
+pub fn current(config: &Config) -> Result<Option<Timer>, Error> {
+    let path = timer_path(config);
+
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let mut contents = String::new();
+    try!(BufReader::new(try!(File::open(&path))).read_to_string(&mut contents));
+
+    Ok(Some(try!(json::decode(&contents))))
+}
+
+pub fn save(config: &Config, timer: &Timer) -> Result<(), Error> {
+    let mut file = try!(File::create(timer_path(config)));
+    try!(write!(&mut file, "{}", json::encode(timer).unwrap()));
+
+    Ok(())
+}
+
+pub fn clear(config: &Config) -> Result<(), Error> {
+    let path = timer_path(config);
+
+    if path.exists() {
+        try!(fs::remove_file(&path));
+    }
+
+    Ok(())
+}
+
+fn timer_path(config: &Config) -> PathBuf {
+    config.path().with_file_name(".rdm-timer.json")
+}
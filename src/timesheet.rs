@@ -0,0 +1,542 @@
+/* rdm - A command-line redmine client
+ * Copyright (C) 2015 Renato Zannon
+ *
+ * This program is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation; either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program; if not, see <http://www.gnu.org/licenses/>. */
+
+use std::cmp;
+use std::fmt::Write;
+
+use time;
+
+use models::TimeEntry;
+
+const DAY_NAMES: [&'static str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+/// An ISO 8601 week (e.g. 2015-W14), which always starts on a monday
+#[derive(Debug, Clone, Copy)]
+pub struct Week {
+    pub year: i64,
+    pub number: i64,
+    monday: i64,
+}
+
+impl Week {
+    /// The week that contains today (local time)
+    pub fn current() -> Week {
+        let now = time::now();
+        let today = days_from_civil(now.tm_year as i64 + 1900, now.tm_mon as i64 + 1, now.tm_mday as i64);
+
+        Week::containing(today)
+    }
+
+    /// Parses a week given as YYYY-Www (e.g. 2015-W14)
+    pub fn parse(week: &str) -> Result<Week, String> {
+        let invalid = || format!("Invalid week '{}': expected YYYY-Www, e.g. 2015-W14", week);
+
+        let parts: Vec<&str> = week.splitn(2, "-W").collect();
+        if parts.len() != 2 {
+            return Err(invalid());
+        }
+
+        let year: i64   = try!(parts[0].parse().map_err(|_| invalid()));
+        let number: i64 = try!(parts[1].parse().map_err(|_| invalid()));
+
+        // January 4th is always on the first week of the year
+        let january_4th = days_from_civil(year, 1, 4);
+        let first_monday = january_4th - weekday(january_4th);
+        let parsed = Week::containing(first_monday + (number - 1) * 7);
+
+        if number < 1 || parsed.year != year {
+            return Err(invalid());
+        }
+
+        Ok(parsed)
+    }
+
+    fn containing(day: i64) -> Week {
+        let monday = day - weekday(day);
+        let (year, _, _) = civil_from_days(monday + 3);
+        let number = (monday + 3 - days_from_civil(year, 1, 1)) / 7 + 1;
+
+        Week { year: year, number: number, monday: monday }
+    }
+
+    /// Every day of the week, as YYYY-MM-DD
+    pub fn days(&self) -> Vec<String> {
+        (0..7).map(|offset| format_day(self.monday + offset)).collect()
+    }
+
+    pub fn first_day(&self) -> String { format_day(self.monday) }
+    pub fn last_day(&self) -> String { format_day(self.monday + 6) }
+}
+
+impl ::std::fmt::Display for Week {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        write!(f, "{}-W{:02}", self.year, self.number)
+    }
+}
+
+/// A row of the timesheet: the time spent on an issue, with an activity, on each day of the week
+#[derive(Debug, Clone)]
+pub struct Row {
+    pub issue: Option<u32>,
+    pub activity: String,
+    pub days: Vec<Vec<TimeEntry>>,
+}
+
+impl Row {
+    fn new(issue: Option<u32>, activity: &str) -> Row {
+        Row {
+            issue: issue,
+            activity: activity.to_string(),
+            days: (0..7).map(|_| Vec::new()).collect(),
+        }
+    }
+
+    fn hours(&self, day: usize) -> f64 {
+        self.days[day].iter().fold(0.0, |total, entry| total + entry.hours)
+    }
+
+    fn total(&self) -> f64 {
+        (0..7).fold(0.0, |total, day| total + self.hours(day))
+    }
+
+    fn matches(&self, issue: Option<u32>, activity: &str) -> bool {
+        self.issue == issue && self.activity.to_lowercase() == activity.to_lowercase()
+    }
+
+    fn issue_label(&self) -> String {
+        self.issue.map(|issue| issue.to_string()).unwrap_or("-".to_string())
+    }
+}
+
+pub struct Timesheet {
+    pub week: Week,
+    pub rows: Vec<Row>,
+}
+
+/// A change to a time entry, derived from an edited timesheet
+#[derive(Debug)]
+pub enum Change {
+    Create { issue: u32, activity: String, date: String, hours: f64 },
+    Update { id: u32, hours: f64 },
+    Delete { id: u32 },
+    Skip(String),
+}
+
+impl Timesheet {
+    pub fn new(week: Week, entries: Vec<TimeEntry>) -> Timesheet {
+        let days = week.days();
+        let mut rows: Vec<Row> = Vec::new();
+
+        for entry in entries.into_iter() {
+            let day = match days.iter().position(|day| *day == entry.spent_on) {
+                Some(day) => day,
+                None      => continue,
+            };
+
+            let issue = entry.issue.as_ref().map(|issue| issue.id);
+
+            let index = match rows.iter().position(|row| row.matches(issue, &entry.activity.name)) {
+                Some(index) => index,
+                None => {
+                    rows.push(Row::new(issue, &entry.activity.name));
+                    rows.len() - 1
+                }
+            };
+
+            rows[index].days[day].push(entry);
+        }
+
+        rows.sort_by(|a, b| (a.issue, &a.activity).cmp(&(b.issue, &b.activity)));
+
+        Timesheet { week: week, rows: rows }
+    }
+
+    /// The grid, with daily and weekly totals
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+
+        writeln!(out, "Timesheet for {} ({} to {})", self.week, self.week.first_day(), self.week.last_day()).unwrap();
+        writeln!(out, "").unwrap();
+
+        let width = self.activity_width();
+        self.write_header(&mut out, "", width, true);
+
+        for row in self.rows.iter() {
+            write!(out, "{:<8}{:<w$}", row.issue_label(), row.activity, w = width).unwrap();
+
+            for day in 0..7 {
+                write!(out, "{:>7}", format_hours(row.hours(day))).unwrap();
+            }
+
+            writeln!(out, "{:>8}", format!("{:.2}", row.total())).unwrap();
+        }
+
+        write!(out, "{:<8}{:<w$}", "Total", "", w = width).unwrap();
+
+        let mut week_total = 0.0;
+        for day in 0..7 {
+            let day_total = self.rows.iter().fold(0.0, |total, row| total + row.hours(day));
+            week_total += day_total;
+
+            write!(out, "{:>7}", format!("{:.2}", day_total)).unwrap();
+        }
+
+        writeln!(out, "{:>8}", format!("{:.2}", week_total)).unwrap();
+
+        out
+    }
+
+    /// The grid as text to be edited: one row per line, with `-` for empty cells
+    pub fn editable_text(&self) -> String {
+        let mut out = String::new();
+
+        writeln!(out, "# Timesheet for {} ({} to {})", self.week, self.week.first_day(), self.week.last_day()).unwrap();
+        writeln!(out, "#").unwrap();
+        writeln!(out, "# Change the hours on any cell; use '-' or 0 to remove the time of a day.").unwrap();
+        writeln!(out, "# New rows can be added as: <issue number> <activity> <mon> ... <sun>").unwrap();
+        writeln!(out, "# Lines starting with '#' are ignored.").unwrap();
+        writeln!(out, "#").unwrap();
+
+        let width = self.activity_width();
+        self.write_header(&mut out, "# ", width, false);
+
+        for row in self.rows.iter() {
+            write!(out, "{:<8}{:<w$}", row.issue_label(), row.activity, w = width).unwrap();
+
+            for day in 0..7 {
+                write!(out, "{:>7}", format_hours(row.hours(day))).unwrap();
+            }
+
+            writeln!(out, "").unwrap();
+        }
+
+        out
+    }
+
+    /// Compares the timesheet with its edited text, listing the changes needed on the server.
+    /// The same issue and activity on more than one line is refused, as it can't be told which
+    /// line is meant.
+    pub fn changes(&self, edited: &str) -> Result<Vec<Change>, String> {
+        let days = self.week.days();
+        let mut changes = Vec::new();
+        let mut seen = vec![false; self.rows.len()];
+        let mut keys: Vec<(Option<u32>, String)> = Vec::new();
+
+        for line in edited.lines() {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with("#") {
+                continue;
+            }
+
+            let (issue, activity, new_hours) = try!(parse_row(line));
+
+            let key = (issue, activity.to_lowercase());
+            if keys.contains(&key) {
+                return Err(format!("Issue {} with activity '{}' is on more than one line",
+                                   issue.map(|issue| format!("#{}", issue)).unwrap_or("-".to_string()), activity));
+            }
+            keys.push(key);
+
+            match self.rows.iter().position(|row| row.matches(issue, &activity)) {
+                Some(index) => {
+                    seen[index] = true;
+
+                    for day in 0..7 {
+                        changes.extend(cell_changes(&self.rows[index], day, &days[day], new_hours[day]).into_iter());
+                    }
+                },
+
+                None => {
+                    for day in 0..7 {
+                        if new_hours[day] <= 0.0 {
+                            continue;
+                        }
+
+                        match issue {
+                            Some(issue) => changes.push(Change::Create {
+                                issue: issue,
+                                activity: activity.clone(),
+                                date: days[day].clone(),
+                                hours: new_hours[day],
+                            }),
+
+                            None => changes.push(Change::Skip(format!(
+                                "{} on {}: time without an issue can't be created from the timesheet",
+                                activity, days[day]))),
+                        }
+                    }
+                },
+            }
+        }
+
+        // Rows removed from the text are handled as if every cell had been cleared
+        for (index, row) in self.rows.iter().enumerate() {
+            if !seen[index] {
+                for day in 0..7 {
+                    changes.extend(cell_changes(row, day, &days[day], 0.0).into_iter());
+                }
+            }
+        }
+
+        Ok(changes)
+    }
+
+    fn activity_width(&self) -> usize {
+        let longest = self.rows.iter().map(|row| row.activity.chars().count()).max().unwrap_or(0);
+        cmp::max(longest, 8) + 2
+    }
+
+    fn write_header(&self, out: &mut String, prefix: &str, width: usize, with_total: bool) {
+        let days = self.week.days();
+
+        write!(out, "{:<8}{:<w$}", format!("{}Issue", prefix), "Activity", w = width).unwrap();
+
+        for day in 0..7 {
+            // e.g. "Mon 30"
+            write!(out, "{:>7}", format!("{} {}", DAY_NAMES[day], &days[day][8..])).unwrap();
+        }
+
+        if with_total {
+            write!(out, "{:>8}", "Total").unwrap();
+        }
+
+        writeln!(out, "").unwrap();
+    }
+}
+
+fn cell_changes(row: &Row, day: usize, date: &str, new_hours: f64) -> Vec<Change> {
+    let entries = &row.days[day];
+    let old_hours = row.hours(day);
+
+    if (old_hours - new_hours).abs() < 0.005 {
+        return Vec::new();
+    }
+
+    if new_hours <= 0.0 {
+        return entries.iter().map(|entry| Change::Delete { id: entry.id }).collect();
+    }
+
+    match entries.len() {
+        0 => match row.issue {
+            Some(issue) => vec![Change::Create {
+                issue: issue,
+                activity: row.activity.clone(),
+                date: date.to_string(),
+                hours: new_hours,
+            }],
+
+            None => vec![Change::Skip(format!(
+                "{}: time without an issue can't be created from the timesheet", row.activity))],
+        },
+
+        1 => vec![Change::Update { id: entries[0].id, hours: new_hours }],
+
+        // With several entries on a cell, there's no telling which one should shrink
+        _ if new_hours > old_hours => match row.issue {
+            Some(issue) => vec![Change::Create {
+                issue: issue,
+                activity: row.activity.clone(),
+                date: date.to_string(),
+                hours: new_hours - old_hours,
+            }],
+
+            None => vec![Change::Skip(format!(
+                "{}: time without an issue can't be created from the timesheet", row.activity))],
+        },
+
+        count => vec![Change::Skip(format!(
+            "#{} {} on {} has {} time entries; edit them with 'rdm time <entry-id> edit'",
+            row.issue_label(), row.activity, date, count))],
+    }
+}
+
+/// Parses `<issue> <activity...> <mon> <tue> <wed> <thu> <fri> <sat> <sun>`
+fn parse_row(line: &str) -> Result<(Option<u32>, String, Vec<f64>), String> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+
+    if words.len() < 9 {
+        return Err(format!("Invalid timesheet line (expected an issue, an activity and 7 days): {}", line));
+    }
+
+    let issue = match words[0] {
+        "-" => None,
+        number => Some(try!(number.trim_left_matches('#').parse::<u32>()
+                                  .map_err(|_| format!("Invalid issue number '{}' on line: {}", number, line)))),
+    };
+
+    let activity = words[1..words.len() - 7].connect(" ");
+
+    let mut hours = Vec::new();
+    for value in words[words.len() - 7..].iter() {
+        hours.push(match *value {
+            "-" => 0.0,
+            value => try!(value.parse::<f64>()
+                               .map_err(|_| format!("Invalid hours '{}' on line: {}", value, line))),
+        });
+    }
+
+    Ok((issue, activity, hours))
+}
+
+fn format_hours(hours: f64) -> String {
+    if hours == 0.0 { "-".to_string() } else { format!("{:.2}", hours) }
+}
+
+fn format_day(days: i64) -> String {
+    let (year, month, day) = civil_from_days(days);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Day of the week, with monday as 0
+fn weekday(days: i64) -> i64 {
+    // 1970-01-01 was a thursday
+    ((days + 3) % 7 + 7) % 7
+}
+
+/// Days since 1970-01-01 for a date on the proleptic gregorian calendar
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = (if year >= 0 { year } else { year - 399 }) / 400;
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    era * 146097 + day_of_era - 719468
+}
+
+/// The inverse of `days_from_civil`
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = (if days >= 0 { days } else { days - 146096 }) / 146097;
+    let day_of_era = days - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + (if month <= 2 { 1 } else { 0 });
+
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use rustc_serialize::json;
+
+    use models::TimeEntry;
+    use super::{Week, Timesheet, Change};
+
+    fn entry(id: u32, issue: u32, activity: &str, hours: f64, spent_on: &str) -> TimeEntry {
+        json::decode(&format!(r#"{{
+            "id": {}, "project": {{ "id": 1, "name": "rdm" }}, "issue": {{ "id": {} }},
+            "user": {{ "id": 1, "name": "John Doe" }}, "activity": {{ "id": 9, "name": "{}" }},
+            "hours": {}, "spent_on": "{}",
+            "created_on": "2015-04-01T10:00:00Z", "updated_on": "2015-04-01T10:00:00Z"
+        }}"#, id, issue, activity, hours, spent_on)).unwrap()
+    }
+
+    #[test]
+    fn weeks_start_on_the_monday_of_their_iso_week() {
+        let week = Week::parse("2015-W14").unwrap();
+
+        assert_eq!(week.first_day(), "2015-03-30");
+        assert_eq!(week.last_day(), "2015-04-05");
+        assert_eq!(week.to_string(), "2015-W14");
+    }
+
+    #[test]
+    fn the_first_week_may_start_on_the_previous_year() {
+        let week = Week::parse("2015-W01").unwrap();
+
+        assert_eq!(week.first_day(), "2014-12-29");
+        assert_eq!(week.days().len(), 7);
+        assert_eq!(week.days()[6], "2015-01-04");
+    }
+
+    #[test]
+    fn only_long_years_have_a_53rd_week() {
+        assert_eq!(Week::parse("2020-W53").unwrap().first_day(), "2020-12-28");
+        assert!(Week::parse("2016-W53").is_err());
+    }
+
+    #[test]
+    fn malformed_weeks_are_rejected() {
+        assert!(Week::parse("2015-14").is_err());
+        assert!(Week::parse("2015-W00").is_err());
+        assert!(Week::parse("2015-Wxx").is_err());
+    }
+
+    #[test]
+    fn changed_cells_update_their_entry() {
+        let week = Week::parse("2015-W14").unwrap();
+        let sheet = Timesheet::new(week, vec![entry(40, 1, "Development", 2.0, "2015-03-30")]);
+
+        let changes = sheet.changes("1 Development 3 - - - - - -").unwrap();
+
+        assert_eq!(changes.len(), 1);
+        match changes[0] {
+            Change::Update { id, hours } => {
+                assert_eq!(id, 40);
+                assert_eq!(hours, 3.0);
+            },
+            ref other => panic!("unexpected change: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn removed_rows_delete_their_entries() {
+        let week = Week::parse("2015-W14").unwrap();
+        let sheet = Timesheet::new(week, vec![entry(40, 1, "Development", 2.0, "2015-03-30")]);
+
+        let changes = sheet.changes("# nothing left").unwrap();
+
+        assert_eq!(changes.len(), 1);
+        match changes[0] {
+            Change::Delete { id } => assert_eq!(id, 40),
+            ref other => panic!("unexpected change: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn new_rows_create_entries() {
+        let week = Week::parse("2015-W14").unwrap();
+        let sheet = Timesheet::new(week, Vec::new());
+
+        let changes = sheet.changes("12 Code review - 1.5 - - - - -").unwrap();
+
+        assert_eq!(changes.len(), 1);
+        match changes[0] {
+            Change::Create { issue, ref activity, ref date, hours } => {
+                assert_eq!(issue, 12);
+                assert_eq!(activity, "Code review");
+                assert_eq!(date, "2015-03-31");
+                assert_eq!(hours, 1.5);
+            },
+            ref other => panic!("unexpected change: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn duplicate_rows_are_rejected() {
+        let week = Week::parse("2015-W14").unwrap();
+        let sheet = Timesheet::new(week, Vec::new());
+
+        let edited = "12 Development 1 - - - - - -\n12 development - 2 - - - - -";
+
+        assert!(sheet.changes(edited).is_err());
+    }
+}
@@ -18,6 +18,7 @@ use std::io::prelude::*;
 use std::io::{self, BufReader};
 use std::fs::File;
 use std::path::{Path, PathBuf};
+use std::collections::HashMap;
 
 use std::error::Error;
 use std::{env, fmt};
@@ -25,11 +26,18 @@ use std::{env, fmt};
 use rustc_serialize::json;
 use url::Url;
 
+use timer::Rounding;
+
 #[derive(RustcDecodable, Debug, Clone)]
 struct ConfigData {
     redmine_key: String,
     redmine_url: Url,
     default_close_status: Option<String>,
+    default_project: Option<String>,
+    timer_rounding_minutes: Option<u32>,
+    timer_rounding: Option<String>,
+    changelog_title: Option<String>,
+    changelog_headings: Option<HashMap<String, String>>,
 }
 
 #[derive(Debug, Clone)]
@@ -45,6 +53,40 @@ impl Config {
     pub fn default_close_status(&self) -> Option<&str> {
         self.data.default_close_status.as_ref().map(|s| &s[..])
     }
+    pub fn default_project(&self) -> Option<&str> {
+        self.data.default_project.as_ref().map(|s| &s[..])
+    }
+    pub fn timer_rounding_minutes(&self) -> u32 {
+        self.data.timer_rounding_minutes.unwrap_or(1)
+    }
+    /// How timers are rounded when stopped: "up", "down" or "nearest" (the default)
+    pub fn timer_rounding(&self) -> Rounding {
+        match self.data.timer_rounding.as_ref().map(|s| &s[..]) {
+            Some("up")   => Rounding::Up,
+            Some("down") => Rounding::Down,
+            _            => Rounding::Nearest,
+        }
+    }
+    /// The changelog title template; `{version}`, `{project}` and `{date}` are replaced
+    pub fn changelog_title(&self) -> &str {
+        self.data.changelog_title.as_ref().map(|s| &s[..]).unwrap_or("{version} ({date})")
+    }
+    /// The changelog heading for issues of `tracker`, which defaults to the tracker name
+    pub fn changelog_heading<'a>(&'a self, tracker: &'a str) -> &'a str {
+        self.data.changelog_headings.as_ref()
+            .and_then(|headings| headings.get(tracker))
+            .map(|s| &s[..])
+            .unwrap_or(tracker)
+    }
+
+    /// A config read from `src` rather than from a file, for tests
+    #[cfg(test)]
+    pub fn from_json(src: &str) -> Config {
+        Config {
+            data: json::decode(src).unwrap(),
+            path: PathBuf::from(".rdm.json"),
+        }
+    }
 }
 
 #[derive(Debug)]
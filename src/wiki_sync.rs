@@ -0,0 +1,302 @@
+/* rdm - A command-line redmine client
+ * Copyright (C) 2015 Renato Zannon
+ *
+ * This program is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation; either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program; if not, see <http://www.gnu.org/licenses/>. */
+
+use std::io::prelude::*;
+use std::io::{self, BufReader};
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::fmt;
+use std::collections::HashMap;
+
+use rustc_serialize::json;
+
+use models::{WikiPage, WikiPageSummary};
+
+/// A wiki page kept as `<title>.md` on the synced directory. The title is the file name as
+/// Redmine would store it, e.g. `Release_notes` for `release notes.md`.
+#[derive(Debug, Clone)]
+pub struct LocalPage {
+    pub title: String,
+    pub path: PathBuf,
+    pub text: String,
+}
+
+/// What both sides looked like when a page was last synced
+#[derive(RustcDecodable, RustcEncodable, Debug, Clone)]
+pub struct SyncedPage {
+    pub version: u32,
+    pub updated_on: String,
+    pub digest: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    UpToDate,
+    Push,
+    Pull,
+    /// Both sides changed since the last sync
+    Conflict,
+}
+
+/// What a sync ended up doing with a page
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Outcome {
+    Unchanged,
+    Pushed,
+    Pulled,
+    Skipped,
+    Conflict,
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        let desc = match *self {
+            Outcome::Unchanged => "unchanged",
+            Outcome::Pushed    => "pushed",
+            Outcome::Pulled    => "pulled",
+            Outcome::Skipped   => "skipped",
+            Outcome::Conflict  => "conflict",
+        };
+
+        f.pad(desc)
+    }
+}
+
+/// The last synced state of every page, kept on `.rdm-wiki-sync.json` inside the synced
+/// directory (so that it can be committed along with the pages). It is keyed by project, as
+/// nothing prevents syncing the same directory with more than one wiki.
+pub struct SyncState {
+    path: PathBuf,
+    project: String,
+    projects: HashMap<String, HashMap<String, SyncedPage>>,
+}
+
+impl SyncState {
+    pub fn load(dir: &Path, project: &str) -> Result<SyncState, io::Error> {
+        let path = dir.join(".rdm-wiki-sync.json");
+
+        let projects = if path.exists() {
+            let mut contents = String::new();
+            try!(BufReader::new(try!(File::open(&path))).read_to_string(&mut contents));
+
+            try!(json::decode(&contents).map_err(|err| {
+                io::Error::new(io::ErrorKind::InvalidInput, format!("Invalid wiki sync state: {}", err))
+            }))
+        } else {
+            HashMap::new()
+        };
+
+        Ok(SyncState { path: path, project: project.to_string(), projects: projects })
+    }
+
+    pub fn get(&self, title: &str) -> Option<&SyncedPage> {
+        self.projects.get(&self.project).and_then(|pages| pages.get(title))
+    }
+
+    /// Records that `page` and the local `text` are now in sync
+    pub fn record(&mut self, page: &WikiPage, text: &str) -> Result<(), io::Error> {
+        let synced = SyncedPage {
+            version: page.version,
+            updated_on: page.updated_on.clone(),
+            digest: digest(text),
+        };
+
+        self.projects.entry(self.project.clone()).or_insert(HashMap::new())
+            .insert(page.title.clone(), synced);
+
+        let mut file = try!(File::create(&self.path));
+        write!(&mut file, "{}", json::encode(&self.projects).unwrap())
+    }
+}
+
+/// Reads every `.md` file directly inside `dir`, using the file name as the page title. Two
+/// files that Redmine would take for the same page are refused.
+pub fn local_pages(dir: &Path) -> Result<Vec<LocalPage>, io::Error> {
+    let mut pages: Vec<LocalPage> = Vec::new();
+
+    for entry in try!(fs::read_dir(dir)) {
+        let path = try!(entry).path();
+
+        if path.extension().and_then(|ext| ext.to_str()) != Some("md") {
+            continue;
+        }
+
+        let title = match path.file_stem().and_then(|stem| stem.to_str()) {
+            Some(stem) => titleize(stem),
+            None       => continue,
+        };
+
+        if let Some(other) = pages.iter().find(|page| page.title == title) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!(
+                "{} and {} are both the wiki page {}; rename one of them",
+                other.path.display(), path.display(), title)));
+        }
+
+        let mut text = String::new();
+        try!(BufReader::new(try!(File::open(&path))).read_to_string(&mut text));
+
+        pages.push(LocalPage { title: title, path: path, text: text });
+    }
+
+    Ok(pages)
+}
+
+/// Decides what to do with a page, given both sides and what they were when last synced.
+/// Nothing is ever deleted: a page missing on one side is copied from the other.
+pub fn plan(local: Option<&LocalPage>, remote: Option<&WikiPageSummary>, synced: Option<&SyncedPage>) -> Action {
+    let (local, remote) = match (local, remote) {
+        (Some(local), Some(remote)) => (local, remote),
+        (Some(_), None)             => return Action::Push,
+        _                           => return Action::Pull,
+    };
+
+    let (local_changed, remote_changed) = match synced {
+        Some(synced) => (
+            digest(&local.text) != synced.digest,
+            remote.version != synced.version || remote.updated_on != synced.updated_on,
+        ),
+        None => (true, true),
+    };
+
+    match (local_changed, remote_changed) {
+        (false, false) => Action::UpToDate,
+        (true, false)  => Action::Push,
+        (false, true)  => Action::Pull,
+        (true, true)   => Action::Conflict,
+    }
+}
+
+/// Redmine hands out page text with CRLF line endings; files are kept with plain LF
+pub fn normalize(text: &str) -> String {
+    text.replace("\r\n", "\n")
+}
+
+/// The page title Redmine makes of `name`: whitespace becomes `_`, some punctuation is dropped
+/// and the first letter is capitalized (as `Wiki.titleize` does)
+pub fn titleize(name: &str) -> String {
+    let mut title = String::new();
+    let mut in_whitespace = false;
+
+    for chr in name.chars() {
+        if chr.is_whitespace() {
+            if !in_whitespace {
+                title.push('_');
+            }
+
+            in_whitespace = true;
+            continue;
+        }
+
+        in_whitespace = false;
+
+        if !",./?;|:".contains(chr) {
+            title.push(chr);
+        }
+    }
+
+    let mut chars = title.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None        => String::new(),
+    }
+}
+
+/// A 64-bit FNV-1a digest of the normalized text, which stays the same across rdm and Rust
+/// versions (unlike `std::hash`)
+fn digest(text: &str) -> String {
+    let hash = normalize(text).bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    });
+
+    format!("{:016x}", hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use models::WikiPageSummary;
+    use super::{plan, digest, titleize, Action, LocalPage, SyncedPage};
+
+    fn local(text: &str) -> LocalPage {
+        LocalPage { title: "Home".to_string(), path: PathBuf::from("Home.md"), text: text.to_string() }
+    }
+
+    fn remote(version: u32) -> WikiPageSummary {
+        WikiPageSummary {
+            title: "Home".to_string(),
+            parent: None,
+            version: version,
+            created_on: "2015-04-01T10:00:00Z".to_string(),
+            updated_on: format!("2015-04-0{}T10:00:00Z", version),
+        }
+    }
+
+    fn synced(version: u32, text: &str) -> SyncedPage {
+        SyncedPage {
+            version: version,
+            updated_on: format!("2015-04-0{}T10:00:00Z", version),
+            digest: digest(text),
+        }
+    }
+
+    #[test]
+    fn pages_on_one_side_only_are_copied() {
+        assert_eq!(plan(Some(&local("text")), None, None), Action::Push);
+        assert_eq!(plan(None, Some(&remote(1)), None), Action::Pull);
+    }
+
+    #[test]
+    fn pages_never_synced_are_conflicts() {
+        assert_eq!(plan(Some(&local("text")), Some(&remote(1)), None), Action::Conflict);
+    }
+
+    #[test]
+    fn untouched_pages_are_left_alone() {
+        let state = synced(1, "text\r\nmore");
+
+        assert_eq!(plan(Some(&local("text\nmore")), Some(&remote(1)), Some(&state)), Action::UpToDate);
+    }
+
+    #[test]
+    fn the_changed_side_wins() {
+        let state = synced(1, "text");
+
+        assert_eq!(plan(Some(&local("new text")), Some(&remote(1)), Some(&state)), Action::Push);
+        assert_eq!(plan(Some(&local("text")), Some(&remote(2)), Some(&state)), Action::Pull);
+    }
+
+    #[test]
+    fn changes_on_both_sides_are_conflicts() {
+        let state = synced(1, "text");
+
+        assert_eq!(plan(Some(&local("new text")), Some(&remote(2)), Some(&state)), Action::Conflict);
+    }
+
+    #[test]
+    fn file_names_become_redmine_titles() {
+        assert_eq!(titleize("Release Notes"), "Release_Notes");
+        assert_eq!(titleize("release  notes"), "Release_notes");
+        assert_eq!(titleize("FAQ: v1.2?"), "FAQ_v12");
+        assert_eq!(titleize("Home"), "Home");
+    }
+
+    #[test]
+    fn digests_ignore_line_endings() {
+        assert_eq!(digest("a\r\nb"), digest("a\nb"));
+        assert!(digest("a") != digest("b"));
+    }
+}